@@ -1,15 +1,17 @@
 //! An example of extending the `cosy` parser with custom syntax.
 //!
 //! This example demonstrates how to implement the `CosyParserExtension` trait
-//! to support a custom "speech bubble" notation (e.g., `[{ content]`).
+//! to support a custom "speech bubble" notation (e.g., `[{ content]`). A ready-made version is
+//! available as `cosy::extensions::SpeechBubbleExtension` with the `extensions` feature.
 
-use cosy::CosyParserExtension;
+use cosy::ast::Node;
+use cosy::{CosyParserExtension, ExtensionContext};
 
 /// Represents custom syntax elements supported by this extension.
 #[derive(Debug, PartialEq)]
 enum MySyntax {
-    /// A speech bubble containing text.
-    SpeechBubble(String),
+    /// A speech bubble containing inline markup.
+    SpeechBubble(Vec<Node<'static, MySyntax>>),
 }
 
 /// A custom extension for the `cosy` parser.
@@ -20,33 +22,48 @@ impl CosyParserExtension for MyExtension {
 
     /// Parses custom bracketed content.
     ///
-    /// If the content starts with `{ `, it is interpreted as a `SpeechBubble`.
-    fn parse_bracket(&self, content: &str) -> Option<Self::Output> {
-        if let Some(body) = content.strip_prefix("{ ") {
-            Some(MySyntax::SpeechBubble(body.to_string()))
-        } else {
-            None // Return none to fallback to the default parser
+    /// If the content starts with `{ `, it is interpreted as a `SpeechBubble`, and its body is
+    /// parsed as inline markup.
+    fn parse_bracket(
+        &self,
+        content: &str,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        // Returning `None` falls back to the default parser
+        let body = content.strip_prefix("{ ")?;
+        if body.trim().is_empty() {
+            ctx.diagnostic("empty speech bubble");
         }
+        Some(MySyntax::SpeechBubble(ctx.parse_inline(body)))
     }
 
     /// Parses custom block content.
     ///
     /// This extension does not define any custom block-level syntax.
-    fn parse_block(&self, _content: &str) -> Option<Self::Output> {
+    fn parse_block(
+        &self,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
         None
     }
 }
 
 fn main() {
     let extension = MyExtension;
-    let input = "Cheshire Cat[{ We're all mad here.]";
+    let input = "Cheshire Cat[{ We're all [* mad] here.]\nAlice[{ ]";
 
     let mut input_stream = input;
 
-    let result = cosy::parse(&mut input_stream, &extension);
+    let result = cosy::parse_with_diagnostics(&mut input_stream, &extension);
 
     match result {
-        Ok(nodes) => println!("{:#?}", nodes),
+        Ok((nodes, diagnostics)) => {
+            println!("{:#?}", nodes);
+            for diagnostic in diagnostics {
+                println!("line {}: {}", diagnostic.line + 1, diagnostic.message);
+            }
+        }
         Err(e) => println!("Error: {}", e),
     }
 }
@@ -1,9 +1,10 @@
 //! Block-level AST nodes.
 
-use super::node::Node;
+use super::node::{Node, into_owned, owned};
+use std::borrow::Cow;
 
 /// Represents a complete document, which is a sequence of blocks.
-pub type Document<T> = Vec<Block<T>>;
+pub type Document<'a, T> = Vec<Block<'a, T>>;
 
 // --------------------------------------------------------
 // Block level (line-based structure)
@@ -13,18 +14,26 @@ pub type Document<T> = Vec<Block<T>>;
 ///
 /// Blocks are the top-level structures like lines, code blocks, tables, etc.
 #[derive(Debug, PartialEq, Clone)]
-pub struct Block<T> {
+pub struct Block<'a, T> {
     /// The indentation level of the block.
     pub indent: usize,
     /// The actual content of the block.
-    pub content: BlockContent<T>,
+    pub content: BlockContent<'a, T>,
 }
 
 /// The content of a block-level element.
 #[derive(Debug, PartialEq, Clone)]
-pub enum BlockContent<T> {
+pub enum BlockContent<'a, T> {
     /// A normal line of text, composed of a sequence of inline nodes.
-    Line(Vec<Node<T>>),
+    Line(Vec<Node<'a, T>>),
+
+    /// An indented line starting with a number (e.g., ` 1. item`), rendered as an ordered list item.
+    NumberedLine {
+        /// The number written before the dot.
+        number: usize,
+        /// The content after the `1. ` prefix.
+        nodes: Vec<Node<'a, T>>,
+    },
 
     /// A code block with optional filename and indentation.
     CodeBlock {
@@ -32,23 +41,35 @@ pub enum BlockContent<T> {
         /// The indentation level of the code block content.
         indent: usize,
         /// The raw content of the code block.
-        content: String,
+        ///
+        /// Borrowed from the input only for single-line code, since indentation is removed
+        /// from every line.
+        content: Cow<'a, str>,
     },
 
-    /// A table with a name and rows of cells.
+    /// A table with a name, a header row and body rows.
+    ///
+    /// Rows with fewer cells than the widest row are padded with empty cells, so every row
+    /// (including the header) has exactly `columns` cells.
     Table {
         /// The name of the table.
-        name: String,
-        /// The rows of the table, where each cell is a sequence of inline nodes.
+        name: Cow<'a, str>,
+        /// The number of columns.
+        columns: usize,
+        /// The first row of the table, which Cosense renders as the header.
         ///
-        /// Structure: Rows -> Cells -> Content (Nodes)
-        rows: Vec<Vec<Vec<Node<T>>>>,
+        /// Empty if the table has no rows.
+        header: Vec<TableCell<'a, T>>,
+        /// The rows following the header.
+        ///
+        /// Structure: Rows -> Cells
+        rows: Vec<Vec<TableCell<'a, T>>>,
     },
 
     /// A quote block, composed of a sequence of inline nodes.
     ///
     /// Content of quote is also subject to inline parsing.
-    Quote(Vec<Node<T>>),
+    Quote(Vec<Node<'a, T>>),
 
     /// A custom block-level extension.
     ///
@@ -56,6 +77,81 @@ pub enum BlockContent<T> {
     Custom(T),
 }
 
+/// A cell of a table.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TableCell<'a, T> {
+    /// The source text of the cell, as written in the markup.
+    pub raw: Cow<'a, str>,
+    /// The inline nodes parsed from `raw`.
+    pub nodes: Vec<Node<'a, T>>,
+}
+
+impl<T> TableCell<'_, T> {
+    /// Creates an empty cell, used to pad ragged rows.
+    pub fn empty() -> Self {
+        TableCell {
+            raw: Cow::Borrowed(""),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Converts the cell into one that owns its text.
+    pub fn into_owned(self) -> TableCell<'static, T> {
+        TableCell {
+            raw: owned(self.raw),
+            nodes: into_owned(self.nodes),
+        }
+    }
+}
+
+impl<T> Block<'_, T> {
+    /// Converts the block into one that owns its text, detaching it from the parsed input.
+    pub fn into_owned(self) -> Block<'static, T> {
+        Block {
+            indent: self.indent,
+            content: self.content.into_owned(),
+        }
+    }
+}
+
+impl<T> BlockContent<'_, T> {
+    /// Converts the content into one that owns its text.
+    pub fn into_owned(self) -> BlockContent<'static, T> {
+        match self {
+            BlockContent::Line(nodes) => BlockContent::Line(into_owned(nodes)),
+            BlockContent::NumberedLine { number, nodes } => BlockContent::NumberedLine {
+                number,
+                nodes: into_owned(nodes),
+            },
+            BlockContent::CodeBlock {
+                meta,
+                indent,
+                content,
+            } => BlockContent::CodeBlock {
+                meta,
+                indent,
+                content: owned(content),
+            },
+            BlockContent::Table {
+                name,
+                columns,
+                header,
+                rows,
+            } => BlockContent::Table {
+                name: owned(name),
+                columns,
+                header: header.into_iter().map(TableCell::into_owned).collect(),
+                rows: rows
+                    .into_iter()
+                    .map(|row| row.into_iter().map(TableCell::into_owned).collect())
+                    .collect(),
+            },
+            BlockContent::Quote(nodes) => BlockContent::Quote(into_owned(nodes)),
+            BlockContent::Custom(custom) => BlockContent::Custom(custom),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum CodeBlockMeta {
     None,
@@ -0,0 +1,107 @@
+//! Grouped views over a document.
+//!
+//! Each `> ` line is parsed into its own [`BlockContent::Quote`] block. Renderers usually want
+//! one quotation per paragraph instead, so [`group_quotes`] merges consecutive quote lines.
+
+use super::block::{Block, BlockContent};
+use super::node::Node;
+
+/// An item of a document in which consecutive quote lines are merged.
+#[derive(Debug, PartialEq)]
+pub enum Grouped<'a, T> {
+    /// Any block other than a quote.
+    Block(&'a Block<'a, T>),
+    /// One or more consecutive quote lines at the same indentation level.
+    Quote(QuoteGroup<'a, T>),
+}
+
+impl<T> Grouped<'_, T> {
+    /// The indentation level of the item.
+    pub fn indent(&self) -> usize {
+        match self {
+            Grouped::Block(block) => block.indent,
+            Grouped::Quote(group) => group.indent,
+        }
+    }
+}
+
+/// Consecutive quote lines at the same indentation level.
+#[derive(Debug, PartialEq)]
+pub struct QuoteGroup<'a, T> {
+    /// The indentation level shared by the lines (non-zero when nested under a bullet).
+    pub indent: usize,
+    /// The content of each quote line, in order.
+    pub lines: Vec<&'a [Node<'a, T>]>,
+}
+
+/// Merges consecutive quote blocks at the same indentation level into [`QuoteGroup`]s.
+///
+/// A quote at a different indentation level, or any other block in between, starts a new
+/// group. Other blocks are passed through unchanged.
+pub fn group_quotes<'a, T>(document: &'a [Block<'a, T>]) -> Vec<Grouped<'a, T>> {
+    let mut items: Vec<Grouped<'a, T>> = Vec::new();
+
+    for block in document {
+        let BlockContent::Quote(nodes) = &block.content else {
+            items.push(Grouped::Block(block));
+            continue;
+        };
+
+        match items.last_mut() {
+            Some(Grouped::Quote(group)) if group.indent == block.indent => {
+                group.lines.push(nodes);
+            }
+            _ => items.push(Grouped::Quote(QuoteGroup {
+                indent: block.indent,
+                lines: vec![nodes],
+            })),
+        }
+    }
+
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<Block<'_, ()>> {
+        let mut input_stream = input;
+        crate::parse(&mut input_stream, &()).unwrap()
+    }
+
+    #[test]
+    fn group_consecutive_quotes() {
+        let document = parse("> a\n> b\ntext\n> c");
+        let groups = group_quotes(&document);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(
+            groups[0],
+            Grouped::Quote(QuoteGroup {
+                indent: 0,
+                lines: vec![&[Node::Text("a".into())][..], &[Node::Text("b".into())][..],],
+            })
+        );
+        assert_eq!(groups[1], Grouped::Block(&document[2]));
+        assert_eq!(
+            groups[2],
+            Grouped::Quote(QuoteGroup {
+                indent: 0,
+                lines: vec![&[Node::Text("c".into())][..]],
+            })
+        );
+    }
+
+    #[test]
+    fn group_quotes_under_bullets() {
+        let document = parse("bullet\n > a\n > b\n  > deeper\n > c");
+        let indents: Vec<(usize, usize)> = group_quotes(&document)
+            .iter()
+            .map(|item| match item {
+                Grouped::Block(block) => (block.indent, 0),
+                Grouped::Quote(group) => (group.indent, group.lines.len()),
+            })
+            .collect();
+        assert_eq!(indents, vec![(0, 0), (1, 2), (2, 1), (1, 1)]);
+    }
+}
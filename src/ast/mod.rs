@@ -1,7 +1,14 @@
 //! Abstract Syntax Tree (AST) definitions for the parser.
+//!
+//! Text in the tree borrows from the parsed input where it can (`Cow<'a, str>`). Call
+//! `into_owned` on a node, block or page to keep it after the input is dropped.
 
 mod block;
+mod group;
 mod node;
+mod page;
 
 pub use block::*;
+pub use group::*;
 pub use node::*;
+pub use page::*;
@@ -1,45 +1,76 @@
 //! Inline-level AST nodes.
 
+use crate::url::UrlKind;
+use std::borrow::Cow;
+
 // --------------------------------------------------------
 // Inline level (character-based structure)
 // --------------------------------------------------------
 
 /// Represents an inline-level element (node) within a block.
+///
+/// Text is borrowed from the parsed input where possible; use [`Node::into_owned`] to detach
+/// a node from the input.
 #[derive(Debug, PartialEq, Clone)]
-pub enum Node<T> {
+pub enum Node<'a, T> {
     /// Plain text.
-    Text(String),
+    Text(Cow<'a, str>),
 
     /// A link (internal or external).
     ///
     /// Requires `T` because the label contains `Node`s.
-    Link(Link<T>),
+    Link(Link<'a, T>),
 
     /// An image reference.
-    Image(String),
+    Image {
+        /// The URL of the image.
+        src: Cow<'a, str>,
+        /// The link target when the image is wrapped in a link (e.g., `[image-url link-url]`).
+        href: Option<Cow<'a, str>>,
+    },
+
+    /// An embedded media or service (e.g., YouTube video, tweet, audio file).
+    Embed {
+        /// The original URL.
+        url: Cow<'a, str>,
+        /// The kind of embed, including ids extracted from the URL.
+        kind: UrlKind,
+    },
 
     /// An icon reference.
     Icon {
         /// The name of the icon.
-        name: String,
+        name: Cow<'a, str>,
         /// The repetition count of the icon.
         count: usize,
     },
 
+    /// A map location (e.g., `[N35.68,E139.76,Z14]`).
+    Location {
+        /// The latitude in degrees (north is positive).
+        lat: f64,
+        /// The longitude in degrees (east is positive).
+        lon: f64,
+        /// The zoom level of the map, if specified.
+        zoom: Option<u8>,
+        /// The place name, if specified.
+        label: Option<Cow<'a, str>>,
+    },
+
     /// Inline code snippet.
-    InlineCode(String),
+    InlineCode(Cow<'a, str>),
 
     /// Mathematical expression.
-    Math(String),
+    Math(Cow<'a, str>),
 
     /// Decorated text (bold, italic, etc.).
     ///
     /// Requires `T` due to recursive structure.
     Decoration {
         /// The decoration characters (e.g., "*", "*-").
-        decos: String,
+        decos: Cow<'a, str>,
         /// The content inside the decoration.
-        nodes: Vec<Node<T>>,
+        nodes: Vec<Node<'a, T>>,
     },
 
     /// A custom inline-level extension.
@@ -50,18 +81,130 @@ pub enum Node<T> {
 
 /// Represents a link target and optional label.
 #[derive(Debug, PartialEq, Clone)]
-pub enum Link<T> {
+pub enum Link<'a, T> {
     /// A link to another page (internal link).
-    Page(String),
+    Page(Cow<'a, str>),
+    /// A link to a specific line of another page (e.g., `[Page#5f3a...]`).
+    Anchor {
+        /// The name of the linked page.
+        page: Cow<'a, str>,
+        /// The fragment after `#`, usually a line id.
+        anchor: Cow<'a, str>,
+    },
     /// A raw URL (external link).
-    Url(String),
+    Url(Cow<'a, str>),
     /// A link with an explicit label.
     WithLabel {
         /// The destination URL or page name.
-        href: String,
+        href: Cow<'a, str>,
         /// The label content, which can contain other inline nodes.
         ///
         /// The label might contain `Custom` nodes.
-        label: Vec<Node<T>>,
+        label: Vec<Node<'a, T>>,
     },
 }
+
+impl<T> Node<'_, T> {
+    /// Converts the node into one that owns its text.
+    pub fn into_owned(self) -> Node<'static, T> {
+        match self {
+            Node::Text(text) => Node::Text(owned(text)),
+            Node::Link(link) => Node::Link(link.into_owned()),
+            Node::Image { src, href } => Node::Image {
+                src: owned(src),
+                href: href.map(owned),
+            },
+            Node::Embed { url, kind } => Node::Embed {
+                url: owned(url),
+                kind,
+            },
+            Node::Icon { name, count } => Node::Icon {
+                name: owned(name),
+                count,
+            },
+            Node::Location {
+                lat,
+                lon,
+                zoom,
+                label,
+            } => Node::Location {
+                lat,
+                lon,
+                zoom,
+                label: label.map(owned),
+            },
+            Node::InlineCode(code) => Node::InlineCode(owned(code)),
+            Node::Math(tex) => Node::Math(owned(tex)),
+            Node::Decoration { decos, nodes } => Node::Decoration {
+                decos: owned(decos),
+                nodes: into_owned(nodes),
+            },
+            Node::Custom(custom) => Node::Custom(custom),
+        }
+    }
+
+    /// Returns the text content of the node without markup.
+    ///
+    /// Links yield their page name, URL or label, and icons yield their name. Images, embeds
+    /// and custom nodes have no text.
+    pub fn plain_text(&self) -> String {
+        let mut out = String::new();
+        self.push_plain_text(&mut out);
+        out
+    }
+
+    fn push_plain_text(&self, out: &mut String) {
+        match self {
+            Node::Text(text) | Node::InlineCode(text) | Node::Math(text) => out.push_str(text),
+            Node::Link(Link::Page(name)) | Node::Link(Link::Url(name)) => out.push_str(name),
+            Node::Link(Link::Anchor { page, .. }) => out.push_str(page),
+            Node::Link(Link::WithLabel { label, .. }) => {
+                label.iter().for_each(|node| node.push_plain_text(out))
+            }
+            Node::Icon { name, .. } => out.push_str(name),
+            Node::Location { label, .. } => out.push_str(label.as_deref().unwrap_or_default()),
+            Node::Decoration { nodes, .. } => {
+                nodes.iter().for_each(|node| node.push_plain_text(out))
+            }
+            Node::Image { .. } | Node::Embed { .. } | Node::Custom(_) => {}
+        }
+    }
+}
+
+impl<T> Link<'_, T> {
+    /// Converts the link into one that owns its text.
+    pub fn into_owned(self) -> Link<'static, T> {
+        match self {
+            Link::Page(name) => Link::Page(owned(name)),
+            Link::Anchor { page, anchor } => Link::Anchor {
+                page: owned(page),
+                anchor: owned(anchor),
+            },
+            Link::Url(url) => Link::Url(owned(url)),
+            Link::WithLabel { href, label } => Link::WithLabel {
+                href: owned(href),
+                label: into_owned(label),
+            },
+        }
+    }
+}
+
+/// Converts a sequence of nodes into nodes that own their text.
+pub fn into_owned<T>(nodes: Vec<Node<'_, T>>) -> Vec<Node<'static, T>> {
+    nodes.into_iter().map(Node::into_owned).collect()
+}
+
+pub(crate) fn owned(s: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(s.into_owned())
+}
+
+/// Concatenates the plain text of a sequence of nodes.
+///
+/// See [`Node::plain_text`].
+pub fn plain_text<T>(nodes: &[Node<'_, T>]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        node.push_plain_text(&mut out);
+    }
+    out
+}
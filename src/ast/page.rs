@@ -0,0 +1,22 @@
+//! Page-level AST nodes.
+
+use super::block::{Block, Document};
+
+/// Represents a page, whose first line is its title.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Page<'a, T> {
+    /// The title of the page as plain text, without decorations or indentation.
+    pub title: String,
+    /// The blocks following the title line.
+    pub body: Document<'a, T>,
+}
+
+impl<T> Page<'_, T> {
+    /// Converts the page into one that owns its text, detaching it from the parsed input.
+    pub fn into_owned(self) -> Page<'static, T> {
+        Page {
+            title: self.title,
+            body: self.body.into_iter().map(Block::into_owned).collect(),
+        }
+    }
+}
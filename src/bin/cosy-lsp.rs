@@ -0,0 +1,10 @@
+//! A language server for Cosense pages, speaking the Language Server Protocol over stdio.
+//!
+//! Build with `cargo build --features lsp --bin cosy-lsp` and point the editor's LSP client at
+//! the binary for `.cosense` and `.txt` files.
+
+use std::io;
+
+fn main() -> io::Result<()> {
+    cosy::lsp::run(io::stdin().lock(), io::stdout().lock())
+}
@@ -0,0 +1,480 @@
+//! Extensions chosen at runtime.
+//!
+//! [`CosyParserExtension`] has an associated output type, so a set of plugins loaded from
+//! configuration cannot be held as trait objects. [`DynExtension`] is its object-safe
+//! counterpart: plugins return boxed [`CustomNode`]s, which can be downcast back to their
+//! concrete types. An [`ExtensionRegistry`] holds plugins by name, lets them be enabled or
+//! disabled, and is itself an extension to pass to [`crate::parse`].
+
+use crate::ast::Node;
+use crate::html::RenderHtml;
+use crate::markdown::RenderMarkdown;
+use crate::url::{Url, UrlKind};
+use crate::{BracketKind, CosyParserExtension, ExtensionContext, InlineSpan};
+use std::any::Any;
+use std::fmt::Debug;
+
+/// A custom node produced by a [`DynExtension`].
+///
+/// Implement it for a type that is `Clone + PartialEq`; the object-safe helpers come from
+/// [`CustomNodeShims`]. Rendering is optional: a node that also implements [`RenderHtml`] or
+/// [`RenderMarkdown`] opts in by returning itself from [`CustomNode::as_html`] or
+/// [`CustomNode::as_markdown`], and is left out of the output otherwise.
+pub trait CustomNode: Debug + Send + Sync + CustomNodeShims {
+    /// Returns the node as [`RenderHtml`] if it can be rendered to HTML.
+    fn as_html(&self) -> Option<&dyn RenderHtml> {
+        None
+    }
+
+    /// Returns the node as [`RenderMarkdown`] if it can be rendered to Markdown.
+    fn as_markdown(&self) -> Option<&dyn RenderMarkdown> {
+        None
+    }
+}
+
+/// Object-safe stand-ins for `Any`, `Clone` and `PartialEq`, implemented for every
+/// [`CustomNode`] that is `Clone + PartialEq`.
+pub trait CustomNodeShims {
+    /// Returns `self` as `Any`, for downcasting.
+    fn as_any(&self) -> &dyn Any;
+    /// Clones `self` into a new box.
+    fn clone_box(&self) -> Box<dyn CustomNode>;
+    /// Compares `self` with a node of any type; nodes of different types are never equal.
+    fn eq_dyn(&self, other: &dyn CustomNode) -> bool;
+}
+
+impl<T: CustomNode + Clone + PartialEq + 'static> CustomNodeShims for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn CustomNode> {
+        Box::new(self.clone())
+    }
+
+    fn eq_dyn(&self, other: &dyn CustomNode) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+}
+
+impl dyn CustomNode {
+    /// Returns the node as `T` if it is one.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    /// Returns whether the node is a `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+}
+
+impl Clone for Box<dyn CustomNode> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl PartialEq for dyn CustomNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_dyn(other)
+    }
+}
+
+impl RenderHtml for Box<dyn CustomNode> {
+    fn render_html(&self, out: &mut String) {
+        if let Some(node) = self.as_html() {
+            node.render_html(out);
+        }
+    }
+}
+
+impl RenderMarkdown for Box<dyn CustomNode> {
+    fn render_markdown(&self, out: &mut String) {
+        if let Some(node) = self.as_markdown() {
+            node.render_markdown(out);
+        }
+    }
+}
+
+/// An object-safe parser extension, the runtime counterpart of [`CosyParserExtension`].
+///
+/// The context is that of the registry, so nested inline markup is parsed with every enabled
+/// plugin.
+pub trait DynExtension: Send + Sync {
+    /// Returns the name used to enable or disable the extension.
+    fn name(&self) -> &str;
+
+    /// Parses the content inside brackets, like [`CosyParserExtension::parse_bracket`].
+    fn parse_bracket(
+        &self,
+        content: &str,
+        ctx: &ExtensionContext<'_, ExtensionRegistry>,
+    ) -> Option<Box<dyn CustomNode>>;
+
+    /// Replaces a built-in bracket form, like [`CosyParserExtension::before_builtin`].
+    fn before_builtin(
+        &self,
+        _kind: BracketKind,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, ExtensionRegistry>,
+    ) -> Option<Box<dyn CustomNode>> {
+        None
+    }
+
+    /// Parses what would otherwise be a page link, like [`CosyParserExtension::fallback`].
+    fn fallback(
+        &self,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, ExtensionRegistry>,
+    ) -> Option<Box<dyn CustomNode>> {
+        None
+    }
+
+    /// Finds the first custom span in plain text, like [`CosyParserExtension::find_inline`].
+    fn find_inline(&self, _text: &str) -> Option<InlineSpan> {
+        None
+    }
+
+    /// Parses a span found by [`Self::find_inline`], like
+    /// [`CosyParserExtension::parse_inline_span`].
+    fn parse_inline_span(
+        &self,
+        _text: &str,
+        _span: InlineSpan,
+        _ctx: &ExtensionContext<'_, ExtensionRegistry>,
+    ) -> Option<Box<dyn CustomNode>> {
+        None
+    }
+
+    /// Classifies a bracketed URL, like [`CosyParserExtension::classify_url`].
+    fn classify_url(&self, _url: &Url) -> Option<UrlKind> {
+        None
+    }
+}
+
+struct Entry {
+    extension: Box<dyn DynExtension>,
+    enabled: bool,
+}
+
+/// A set of [`DynExtension`]s, tried in registration order.
+///
+/// The registry is a [`CosyParserExtension`] whose output is `Box<dyn CustomNode>`; the first
+/// enabled extension that parses the content wins.
+#[derive(Default)]
+pub struct ExtensionRegistry {
+    entries: Vec<Entry>,
+}
+
+impl ExtensionRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an enabled extension after the existing ones.
+    pub fn register(&mut self, extension: impl DynExtension + 'static) -> &mut Self {
+        self.entries.push(Entry {
+            extension: Box::new(extension),
+            enabled: true,
+        });
+        self
+    }
+
+    /// Enables the extensions named `name`. Returns `false` if there is none.
+    pub fn enable(&mut self, name: &str) -> bool {
+        self.set_enabled(name, true)
+    }
+
+    /// Disables the extensions named `name`. Returns `false` if there is none.
+    pub fn disable(&mut self, name: &str) -> bool {
+        self.set_enabled(name, false)
+    }
+
+    /// Returns whether an extension named `name` is registered and enabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.enabled && entry.extension.name() == name)
+    }
+
+    /// Returns the names of the registered extensions, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.extension.name())
+    }
+
+    fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for entry in &mut self.entries {
+            if entry.extension.name() == name {
+                entry.enabled = enabled;
+                found = true;
+            }
+        }
+        found
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &dyn DynExtension> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.extension.as_ref())
+    }
+}
+
+impl CosyParserExtension for ExtensionRegistry {
+    type Output = Box<dyn CustomNode>;
+
+    fn parse_bracket(
+        &self,
+        content: &str,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        self.enabled()
+            .find_map(|extension| extension.parse_bracket(content, ctx))
+    }
+
+    fn parse_block(
+        &self,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        None
+    }
+
+    fn before_builtin(
+        &self,
+        kind: BracketKind,
+        content: &str,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        self.enabled()
+            .find_map(|extension| extension.before_builtin(kind, content, ctx))
+    }
+
+    fn fallback(&self, content: &str, ctx: &ExtensionContext<'_, Self>) -> Option<Self::Output> {
+        self.enabled()
+            .find_map(|extension| extension.fallback(content, ctx))
+    }
+
+    // The earliest span wins, and the first registered extension on a tie
+    fn find_inline(&self, text: &str) -> Option<InlineSpan> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.enabled)
+            .filter_map(|(index, entry)| Some(entry.extension.find_inline(text)?.found_by(index)))
+            .reduce(|first, other| {
+                if other.range.start < first.range.start {
+                    other
+                } else {
+                    first
+                }
+            })
+    }
+
+    // Only the extension that found the span parses it
+    fn parse_inline_span(
+        &self,
+        text: &str,
+        span: InlineSpan,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        let (index, span) = span.take_finder()?;
+        let entry = self.entries.get(index)?;
+        entry.extension.parse_inline_span(text, span, ctx)
+    }
+
+    fn classify_url(&self, url: &Url) -> Option<UrlKind> {
+        self.enabled()
+            .find_map(|extension| extension.classify_url(url))
+    }
+}
+
+/// The nodes of a document parsed with an [`ExtensionRegistry`].
+pub type DynNode<'a> = Node<'a, Box<dyn CustomNode>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Bubble(Vec<DynNode<'static>>);
+
+    impl CustomNode for Bubble {
+        fn as_html(&self) -> Option<&dyn RenderHtml> {
+            Some(self)
+        }
+
+        fn as_markdown(&self) -> Option<&dyn RenderMarkdown> {
+            Some(self)
+        }
+    }
+
+    impl RenderHtml for Bubble {
+        fn render_html(&self, out: &mut String) {
+            out.push_str("<div class=\"bubble\">");
+            out.push_str(&crate::html::render_nodes(&self.0));
+            out.push_str("</div>");
+        }
+    }
+
+    impl RenderMarkdown for Bubble {
+        fn render_markdown(&self, out: &mut String) {
+            out.push_str(&crate::markdown::render_nodes(&self.0));
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Shout(String);
+
+    // Only rendered to HTML
+    impl CustomNode for Shout {
+        fn as_html(&self) -> Option<&dyn RenderHtml> {
+            Some(self)
+        }
+    }
+
+    impl RenderHtml for Shout {
+        fn render_html(&self, out: &mut String) {
+            out.push_str(&self.0);
+        }
+    }
+
+    struct BubbleExtension;
+    impl DynExtension for BubbleExtension {
+        fn name(&self) -> &str {
+            "bubble"
+        }
+
+        fn parse_bracket(
+            &self,
+            content: &str,
+            ctx: &ExtensionContext<'_, ExtensionRegistry>,
+        ) -> Option<Box<dyn CustomNode>> {
+            let body = content.strip_prefix("{ ")?;
+            Some(Box::new(Bubble(ctx.parse_inline(body))))
+        }
+    }
+
+    struct ShoutExtension;
+    impl DynExtension for ShoutExtension {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn parse_bracket(
+            &self,
+            content: &str,
+            _ctx: &ExtensionContext<'_, ExtensionRegistry>,
+        ) -> Option<Box<dyn CustomNode>> {
+            let body = content.strip_prefix("^ ")?;
+            Some(Box::new(Shout(body.to_uppercase())))
+        }
+    }
+
+    fn parse<'a>(input: &'a str, registry: &ExtensionRegistry) -> Vec<DynNode<'a>> {
+        let mut input_stream = input;
+        let mut blocks = crate::parse(&mut input_stream, registry).unwrap();
+        match blocks.remove(0).content {
+            BlockContent::Line(nodes) => nodes,
+            content => panic!("unexpected block: {content:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_with_registry() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register(BubbleExtension).register(ShoutExtension);
+
+        let nodes = parse("[{ hey [^ you]]", &registry);
+
+        let Node::Custom(bubble) = &nodes[0] else {
+            panic!("expected a custom node");
+        };
+        let bubble = bubble.downcast_ref::<Bubble>().unwrap();
+        let Node::Custom(shout) = &bubble.0[1] else {
+            panic!("expected a nested custom node");
+        };
+        assert!(!shout.is::<Bubble>());
+        assert_eq!(shout.downcast_ref::<Shout>(), Some(&Shout("YOU".into())));
+        assert_eq!(
+            crate::html::render_nodes(&nodes),
+            "<div class=\"bubble\">hey YOU</div>"
+        );
+        assert_eq!(crate::markdown::render_nodes(&nodes), "hey ");
+    }
+
+    #[test]
+    fn disable_extension() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register(BubbleExtension).register(ShoutExtension);
+
+        assert!(registry.disable("shout"));
+        assert!(!registry.disable("missing"));
+        assert!(!registry.is_enabled("shout"));
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["bubble", "shout"]);
+        assert_eq!(
+            parse("[^ you]", &registry),
+            vec![Node::Link(Link::Page("^ you".into()))]
+        );
+
+        assert!(registry.enable("shout"));
+        let nodes = parse("[^ you]", &registry);
+        assert_eq!(nodes.clone(), nodes);
+        assert_ne!(
+            nodes[0],
+            Node::Custom(Box::new(Shout("you".into())) as Box<dyn CustomNode>)
+        );
+    }
+
+    // Claims words starting with `prefix`, reporting each one it parses
+    struct Tag(&'static str);
+    impl DynExtension for Tag {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn parse_bracket(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, ExtensionRegistry>,
+        ) -> Option<Box<dyn CustomNode>> {
+            None
+        }
+
+        fn find_inline(&self, text: &str) -> Option<InlineSpan> {
+            let start = text.find(self.0)?;
+            let len = text[start..].find(' ').unwrap_or(text.len() - start);
+            Some(InlineSpan::new(start..start + len))
+        }
+
+        fn parse_inline_span(
+            &self,
+            text: &str,
+            span: InlineSpan,
+            ctx: &ExtensionContext<'_, ExtensionRegistry>,
+        ) -> Option<Box<dyn CustomNode>> {
+            ctx.diagnostic(self.0);
+            Some(Box::new(Shout(text[span.range].to_string())))
+        }
+    }
+
+    #[test]
+    fn parse_inline_span_with_the_extension_that_found_it() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register(Tag("#")).register(Tag("@"));
+
+        let mut input_stream = "@a #b @c";
+        let (blocks, diagnostics) =
+            crate::parse_with_diagnostics(&mut input_stream, &registry).unwrap();
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, ["@", "#", "@"]);
+        assert_eq!(
+            crate::html::render(&blocks),
+            "<div class=\"line\">@a #b @c</div>\n"
+        );
+    }
+}
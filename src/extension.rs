@@ -1,17 +1,351 @@
+use crate::TokenKind;
+use crate::ast::{Node, into_owned};
+use crate::parser::Recorder;
+use crate::url::{Url, UrlKind};
+use std::cell::RefCell;
+use std::ops::Range;
+
 // The trait for parsing custom extensions in a markup language
 pub trait CosyParserExtension {
     type Output;
     // Parse the content inside brackets and return an optional custom output
-    fn parse_bracket(&self, content: &str) -> Option<Self::Output>;
-    fn parse_block(&self, content: &str) -> Option<Self::Output>;
+    fn parse_bracket(
+        &self,
+        content: &str,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output>;
+    fn parse_block(&self, content: &str, ctx: &ExtensionContext<'_, Self>) -> Option<Self::Output>;
+    // Replace a built-in bracket form (e.g., icons) after `parse_bracket` declined the content
+    fn before_builtin(
+        &self,
+        _kind: BracketKind,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        None
+    }
+    // Parse a bracket that would otherwise become a page link (e.g., to resolve an alias)
+    fn fallback(&self, _content: &str, _ctx: &ExtensionContext<'_, Self>) -> Option<Self::Output> {
+        None
+    }
+    // Find the first custom span (e.g., `@mention`) in plain text outside brackets. The text
+    // before the span stays plain text and is not scanned again. Finding has no context to
+    // report to, since it may run more than once on the same text (e.g., by composed extensions
+    // that lose to an earlier span)
+    fn find_inline(&self, _text: &str) -> Option<InlineSpan> {
+        None
+    }
+    // Parse the span of `text` found by `find_inline`. The span stays plain text if this
+    // returns `None`, and the text after it is scanned again
+    fn parse_inline_span(
+        &self,
+        _text: &str,
+        _span: InlineSpan,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        None
+    }
+    // Classify a bracketed URL before the built-in rules (e.g., to embed an internal video host)
+    fn classify_url(&self, _url: &Url) -> Option<UrlKind> {
+        None
+    }
+}
+
+// The built-in form a bracket would be parsed as, see `before_builtin`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BracketKind {
+    // [$ x^2]
+    Math,
+    // [* bold]
+    Decoration,
+    // [name.icon]
+    Icon,
+    // [N35.68,E139.76,Z14]
+    Location,
+    // [https://example.com/image.png], optionally with a link
+    Image,
+    // [https://youtu.be/...]
+    Embed,
+    // [https://example.com]
+    Url,
+    // [https://example.com label] or [label https://example.com]
+    LabeledUrl,
+    // [Page] or [Page#anchor]
+    PageLink,
+}
+
+// A span of plain text found by `find_inline`, handed back to `parse_inline_span`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InlineSpan {
+    // The byte range of the span in the text
+    pub range: Range<usize>,
+    // Which extension found the span in each composition around it, the outermost last, so
+    // that only that extension parses it without searching the text again
+    route: Vec<usize>,
+}
+
+impl InlineSpan {
+    pub fn new(range: Range<usize>) -> Self {
+        InlineSpan {
+            range,
+            route: Vec::new(),
+        }
+    }
+
+    // The span found by the `index`th extension of a composition
+    pub(crate) fn found_by(mut self, index: usize) -> Self {
+        self.route.push(index);
+        self
+    }
+
+    // The index of the extension of the outermost composition that found the span, and the
+    // span as that extension found it
+    pub(crate) fn take_finder(mut self) -> Option<(usize, Self)> {
+        let index = self.route.pop()?;
+        Some((index, self))
+    }
+}
+
+impl From<Range<usize>> for InlineSpan {
+    fn from(range: Range<usize>) -> Self {
+        InlineSpan::new(range)
+    }
+}
+
+// A message reported by an extension while parsing
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    // The 0-based index of the line in the parsed input
+    pub line: usize,
+    pub message: String,
+}
+
+// The state of the parser handed to extensions: where the content is, how to parse nested
+// inline markup and where to report problems
+pub struct ExtensionContext<'a, E: ?Sized> {
+    extension: &'a E,
+    indent: usize,
+    line: usize,
+    diagnostics: &'a RefCell<Vec<Diagnostic>>,
+    // Where the parsers record tokens while tokenizing, and the kind of plain text here
+    tokens: Option<&'a Recorder>,
+    text: TokenKind,
+}
+
+// Not derived, which would require `E: Clone`
+impl<E: ?Sized> Clone for ExtensionContext<'_, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E: ?Sized> Copy for ExtensionContext<'_, E> {}
+
+impl<'a, E: ?Sized> ExtensionContext<'a, E> {
+    pub(crate) fn new(extension: &'a E, diagnostics: &'a RefCell<Vec<Diagnostic>>) -> Self {
+        ExtensionContext {
+            extension,
+            indent: 0,
+            line: 0,
+            diagnostics,
+            tokens: None,
+            text: TokenKind::Text,
+        }
+    }
+
+    pub(crate) fn extension(&self) -> &'a E {
+        self.extension
+    }
+
+    // The same context for content at another position
+    pub(crate) fn at(&self, indent: usize, line: usize) -> Self {
+        ExtensionContext {
+            indent,
+            line,
+            ..*self
+        }
+    }
+
+    // The same context, recording the tokens of the input into `recorder`
+    pub(crate) fn recording(&self, recorder: &'a Recorder) -> Self {
+        ExtensionContext {
+            tokens: Some(recorder),
+            ..*self
+        }
+    }
+
+    // The same context without recording, for extensions, which may parse text that is not
+    // part of the input
+    pub(crate) fn unrecorded(&self) -> Self {
+        ExtensionContext {
+            tokens: None,
+            ..*self
+        }
+    }
+
+    // The same context for plain text of another kind (e.g., inside a decoration)
+    pub(crate) fn with_text_kind(&self, text: TokenKind) -> Self {
+        ExtensionContext { text, ..*self }
+    }
+
+    // Record `span`, a slice of the input, as a token if tokenizing
+    pub(crate) fn token(&self, span: &str, kind: TokenKind) {
+        if let Some(recorder) = self.tokens {
+            recorder.push(span, kind);
+        }
+    }
+
+    // Record `span` as plain text of the kind at this position
+    pub(crate) fn text(&self, span: &str) {
+        self.token(span, self.text);
+    }
+
+    // The indent of the block containing the content
+    pub fn indent(&self) -> usize {
+        self.indent
+    }
+
+    // The 0-based index of the line containing the content
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    // Report a problem at the current line, see `parse_with_diagnostics`
+    pub fn diagnostic(&self, message: impl Into<String>) {
+        self.diagnostics.borrow_mut().push(Diagnostic {
+            line: self.line,
+            message: message.into(),
+        });
+    }
+
+    // The same context for another extension, used to delegate to inner extensions
+    pub fn with_extension<'b, F: ?Sized>(&'b self, extension: &'b F) -> ExtensionContext<'b, F> {
+        ExtensionContext {
+            extension,
+            indent: self.indent,
+            line: self.line,
+            diagnostics: self.diagnostics,
+            tokens: self.tokens,
+            text: self.text,
+        }
+    }
+}
+
+impl<E: CosyParserExtension> ExtensionContext<'_, E> {
+    // Parse inline markup (e.g., the body of a custom bracket) with the same extension. The
+    // nodes own their text, since extension outputs cannot borrow from the input
+    pub fn parse_inline(&self, text: &str) -> Vec<Node<'static, E::Output>> {
+        let mut input = text;
+        into_owned(crate::parser::parse_nodes(&mut input, self).unwrap_or_default())
+    }
+}
+
+// The output of two composed extensions: `Left` from the first, `Right` from the second
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+// Composes two extensions; the first one that parses the content wins. Nest tuples to compose
+// more, e.g. `(a, (b, c))`
+impl<A: CosyParserExtension, B: CosyParserExtension> CosyParserExtension for (A, B) {
+    type Output = Either<A::Output, B::Output>;
+    // Nested inline markup parsed by an inner extension only sees that extension
+    fn parse_bracket(
+        &self,
+        content: &str,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        let (a, b) = self;
+        a.parse_bracket(content, &ctx.with_extension(a))
+            .map(Either::Left)
+            .or_else(|| {
+                b.parse_bracket(content, &ctx.with_extension(b))
+                    .map(Either::Right)
+            })
+    }
+    fn parse_block(&self, content: &str, ctx: &ExtensionContext<'_, Self>) -> Option<Self::Output> {
+        let (a, b) = self;
+        a.parse_block(content, &ctx.with_extension(a))
+            .map(Either::Left)
+            .or_else(|| {
+                b.parse_block(content, &ctx.with_extension(b))
+                    .map(Either::Right)
+            })
+    }
+    fn before_builtin(
+        &self,
+        kind: BracketKind,
+        content: &str,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        let (a, b) = self;
+        a.before_builtin(kind, content, &ctx.with_extension(a))
+            .map(Either::Left)
+            .or_else(|| {
+                b.before_builtin(kind, content, &ctx.with_extension(b))
+                    .map(Either::Right)
+            })
+    }
+    fn fallback(&self, content: &str, ctx: &ExtensionContext<'_, Self>) -> Option<Self::Output> {
+        let (a, b) = self;
+        a.fallback(content, &ctx.with_extension(a))
+            .map(Either::Left)
+            .or_else(|| {
+                b.fallback(content, &ctx.with_extension(b))
+                    .map(Either::Right)
+            })
+    }
+    // The earliest span wins, and the first extension on a tie
+    fn find_inline(&self, text: &str) -> Option<InlineSpan> {
+        let (a, b) = self;
+        match (a.find_inline(text), b.find_inline(text)) {
+            (Some(left), Some(right)) if left.range.start <= right.range.start => {
+                Some(left.found_by(0))
+            }
+            (_, Some(right)) => Some(right.found_by(1)),
+            (left, None) => left.map(|left| left.found_by(0)),
+        }
+    }
+    // Only the extension that found the span parses it
+    fn parse_inline_span(
+        &self,
+        text: &str,
+        span: InlineSpan,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        let (a, b) = self;
+        match span.take_finder()? {
+            (0, span) => a
+                .parse_inline_span(text, span, &ctx.with_extension(a))
+                .map(Either::Left),
+            (_, span) => b
+                .parse_inline_span(text, span, &ctx.with_extension(b))
+                .map(Either::Right),
+        }
+    }
+    fn classify_url(&self, url: &Url) -> Option<UrlKind> {
+        self.0
+            .classify_url(url)
+            .or_else(|| self.1.classify_url(url))
+    }
 }
 
 impl CosyParserExtension for () {
     type Output = ();
-    fn parse_bracket(&self, _content: &str) -> Option<Self::Output> {
+    fn parse_bracket(
+        &self,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
         None
     }
-    fn parse_block(&self, _content: &str) -> Option<Self::Output> {
+    fn parse_block(
+        &self,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
         None
     }
 }
@@ -29,17 +363,408 @@ mod tests {
     struct MyExtension;
     impl CosyParserExtension for MyExtension {
         type Output = MySyntax;
-        fn parse_bracket(&self, content: &str) -> Option<Self::Output> {
-            if let Some(body) = content.strip_prefix("{ ") {
-                Some(MySyntax::SpeechBubble(body.to_string()))
-            } else {
-                None
+        fn parse_bracket(
+            &self,
+            content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            content
+                .strip_prefix("{ ")
+                .map(|body| MySyntax::SpeechBubble(body.to_string()))
+        }
+
+        fn parse_block(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
+        }
+    }
+
+    struct Shout;
+    impl CosyParserExtension for Shout {
+        type Output = String;
+        fn parse_bracket(
+            &self,
+            content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            content.strip_prefix("! ").map(str::to_uppercase)
+        }
+
+        fn parse_block(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
+        }
+    }
+
+    struct Fallback;
+    impl CosyParserExtension for Fallback {
+        type Output = ();
+        fn parse_bracket(
+            &self,
+            content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            content.starts_with(['{', '!']).then_some(())
+        }
+
+        fn parse_block(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Bubble {
+        indent: usize,
+        line: usize,
+        nodes: Vec<Node<'static, Bubble>>,
+    }
+
+    struct BubbleExtension;
+    impl CosyParserExtension for BubbleExtension {
+        type Output = Bubble;
+        fn parse_bracket(
+            &self,
+            content: &str,
+            ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            let body = content.strip_prefix("{ ")?;
+            if body.is_empty() {
+                ctx.diagnostic("empty bubble");
             }
+            Some(Bubble {
+                indent: ctx.indent(),
+                line: ctx.line(),
+                nodes: ctx.parse_inline(body),
+            })
+        }
+
+        fn parse_block(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
+        }
+    }
+
+    #[test]
+    fn parse_with_context() {
+        let mut input_stream = "first\n  second\n [{ says [* hi]]\ntable:t\n x\t[{ ]";
+
+        let (blocks, diagnostics) =
+            crate::parse_with_diagnostics(&mut input_stream, &BubbleExtension).unwrap();
+
+        let expected = BlockContent::Line(vec![Node::Custom(Bubble {
+            indent: 1,
+            line: 2,
+            nodes: vec![
+                Node::Text("says ".into()),
+                Node::Decoration {
+                    decos: "*".into(),
+                    nodes: vec![Node::Text("hi".into())],
+                },
+            ],
+        })]);
+        assert_eq!(blocks[2].content, expected);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                line: 4,
+                message: "empty bubble".into(),
+            }]
+        );
+    }
+
+    // Claims `@name` mentions in plain text
+    struct Mention;
+    impl CosyParserExtension for Mention {
+        type Output = String;
+        fn parse_bracket(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
         }
 
-        fn parse_block(&self, _content: &str) -> Option<Self::Output> {
+        fn parse_block(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
             None
         }
+
+        fn find_inline(&self, text: &str) -> Option<InlineSpan> {
+            let start = text.find('@')?;
+            let len = text[start + 1..]
+                .find(|c: char| !c.is_alphanumeric())
+                .unwrap_or(text.len() - start - 1);
+            (len > 0).then(|| InlineSpan::new(start..start + 1 + len))
+        }
+
+        fn parse_inline_span(
+            &self,
+            text: &str,
+            span: InlineSpan,
+            ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            let name = &text[span.range.start + 1..span.range.end];
+            if name == "nobody" {
+                ctx.diagnostic("unknown user");
+            }
+            Some(name.to_string())
+        }
+    }
+
+    // Claims `ABC-123` issue keys in plain text
+    struct IssueKey;
+    impl CosyParserExtension for IssueKey {
+        type Output = u32;
+        fn parse_bracket(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
+        }
+
+        fn parse_block(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
+        }
+
+        fn find_inline(&self, text: &str) -> Option<InlineSpan> {
+            let start = text.find("ABC-")?;
+            let digits = text[start + 4..]
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .count();
+            Some(InlineSpan::new(start..start + 4 + digits))
+        }
+
+        fn parse_inline_span(
+            &self,
+            text: &str,
+            span: InlineSpan,
+            ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            ctx.diagnostic("issue key");
+            text[span.range.start + 4..span.range.end].parse().ok()
+        }
+    }
+
+    #[test]
+    fn parse_inline_spans() {
+        let extension = (Mention, IssueKey);
+        let mut input_stream = "ABC-12 by @alice, see [Page] @bob";
+
+        let blocks = crate::parse(&mut input_stream, &extension).unwrap();
+
+        let expected = BlockContent::Line(vec![
+            Node::Custom(Either::Right(12)),
+            Node::Text(" by ".into()),
+            Node::Custom(Either::Left("alice".into())),
+            Node::Text(", see ".into()),
+            Node::Link(Link::Page("Page".into())),
+            Node::Text(" ".into()),
+            Node::Custom(Either::Left("bob".into())),
+        ]);
+        assert_eq!(blocks[0].content, expected);
+    }
+
+    #[test]
+    fn parse_each_inline_span_once() {
+        // The issue key reports a diagnostic every time it is parsed
+        let extension = (Mention, IssueKey);
+        let mut input_stream = "see @nobody and ABC-1, ABC- or @x ABC-2\n@x ABC-3";
+        let (blocks, diagnostics) =
+            crate::parse_with_diagnostics(&mut input_stream, &extension).unwrap();
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (0, "unknown user"),
+                (0, "issue key"),
+                (0, "issue key"),
+                (0, "issue key"),
+                (1, "issue key"),
+            ]
+        );
+        // A declined span stays text
+        assert_eq!(
+            blocks[0].content,
+            BlockContent::Line(vec![
+                Node::Text("see ".into()),
+                Node::Custom(Either::Left("nobody".into())),
+                Node::Text(" and ".into()),
+                Node::Custom(Either::Right(1)),
+                Node::Text(", ABC- or ".into()),
+                Node::Custom(Either::Left("x".into())),
+                Node::Text(" ".into()),
+                Node::Custom(Either::Right(2)),
+            ])
+        );
+    }
+
+    // Counts how often the inner extension searches text
+    struct Counted<E>(E, std::cell::Cell<usize>);
+    impl<E: CosyParserExtension> CosyParserExtension for Counted<E> {
+        type Output = E::Output;
+        fn parse_bracket(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
+        }
+
+        fn parse_block(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
+        }
+
+        fn find_inline(&self, text: &str) -> Option<InlineSpan> {
+            self.1.set(self.1.get() + 1);
+            self.0.find_inline(text)
+        }
+
+        fn parse_inline_span(
+            &self,
+            text: &str,
+            span: InlineSpan,
+            ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            self.0
+                .parse_inline_span(text, span, &ctx.with_extension(&self.0))
+        }
+    }
+
+    #[test]
+    fn parse_inline_spans_without_searching_again() {
+        let extension = (
+            Counted(Mention, Default::default()),
+            (IssueKey, Counted(Mention, Default::default())),
+        );
+        let mut input_stream = "@a ABC-1 @b";
+
+        let blocks = crate::parse(&mut input_stream, &extension).unwrap();
+
+        assert_eq!(
+            blocks[0].content,
+            BlockContent::Line(vec![
+                Node::Custom(Either::Left("a".into())),
+                Node::Text(" ".into()),
+                Node::Custom(Either::Right(Either::Left(1))),
+                Node::Text(" ".into()),
+                Node::Custom(Either::Left("b".into())),
+            ])
+        );
+        // Once for each span, not again to parse it
+        assert_eq!(extension.0.1.get(), 3);
+        assert_eq!(extension.1.1.1.get(), 3);
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Wiki {
+        Emoji(String),
+        Alias(String),
+    }
+
+    // Renders icons as emoji and resolves page aliases, leaving other brackets to the built-ins
+    struct WikiExtension;
+    impl CosyParserExtension for WikiExtension {
+        type Output = Wiki;
+        fn parse_bracket(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
+        }
+
+        fn parse_block(
+            &self,
+            _content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            None
+        }
+
+        fn before_builtin(
+            &self,
+            kind: BracketKind,
+            content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            match (kind, content) {
+                (BracketKind::Icon, "cat.icon") => Some(Wiki::Emoji("🐱".into())),
+                _ => None,
+            }
+        }
+
+        fn fallback(
+            &self,
+            content: &str,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<Self::Output> {
+            (content == "JS").then(|| Wiki::Alias("JavaScript".into()))
+        }
+    }
+
+    #[test]
+    fn parse_with_builtin_hooks() {
+        let mut input_stream = "[cat.icon][dog.icon][* [JS]][Rust]";
+
+        let blocks = crate::parse(&mut input_stream, &WikiExtension).unwrap();
+
+        let expected = BlockContent::Line(vec![
+            Node::Custom(Wiki::Emoji("🐱".into())),
+            Node::Icon {
+                name: "dog".into(),
+                count: 1,
+            },
+            Node::Decoration {
+                decos: "*".into(),
+                nodes: vec![Node::Custom(Wiki::Alias("JavaScript".into()))],
+            },
+            Node::Link(Link::Page("Rust".into())),
+        ]);
+        assert_eq!(blocks[0].content, expected);
+    }
+
+    #[test]
+    fn parse_composed_extensions() {
+        let extension = (MyExtension, (Shout, Fallback));
+        let mut input_stream = "[{ a][! b][!c]";
+
+        let blocks = crate::parse(&mut input_stream, &extension).unwrap();
+
+        let expected = BlockContent::Line(vec![
+            Node::Custom(Either::Left(MySyntax::SpeechBubble("a".into()))),
+            Node::Custom(Either::Right(Either::Left("B".into()))),
+            Node::Custom(Either::Right(Either::Right(()))),
+        ]);
+        assert_eq!(blocks[0].content, expected);
     }
 
     #[test]
@@ -60,11 +785,11 @@ mod tests {
         assert_eq!(block.indent, 0);
 
         let expected = BlockContent::Line(vec![
-            Node::Text("こんにちは、".to_string()),
-            Node::Custom(MySyntax::SpeechBubble("フキダシ".to_string())),
-            Node::Text(" これは ".to_string()),
-            Node::Link(Link::Page("テスト".to_string())),
-            Node::Text(" です。".to_string()),
+            Node::Text("こんにちは、".into()),
+            Node::Custom(MySyntax::SpeechBubble("フキダシ".into())),
+            Node::Text(" これは ".into()),
+            Node::Link(Link::Page("テスト".into())),
+            Node::Text(" です。".into()),
         ]);
         assert_eq!(block.content, expected);
     }
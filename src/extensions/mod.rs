@@ -0,0 +1,22 @@
+//! Ready-made extensions for notations common in Cosense projects (requires the `extensions`
+//! feature).
+//!
+//! Each extension has a typed output that implements [`RenderHtml`](crate::html::RenderHtml)
+//! and [`RenderMarkdown`](crate::markdown::RenderMarkdown). Compose several with tuples, e.g.
+//! `(SpeechBubbleExtension, RightAlignExtension)`.
+//!
+//! The content of a bubble, an aligned line or a decoration is parsed with its own extension
+//! only, since its nodes hold that extension's output. Composed this way, `[{ [< text]]` is a
+//! bubble holding a generic `<` decoration, not an aligned line. To nest notations of different
+//! extensions, register them as [`DynExtension`](crate::dynamic::DynExtension)s in an
+//! [`ExtensionRegistry`](crate::dynamic::ExtensionRegistry) instead.
+
+mod right_align;
+mod ruby;
+mod speech_bubble;
+mod user_css;
+
+pub use right_align::*;
+pub use ruby::*;
+pub use speech_bubble::*;
+pub use user_css::*;
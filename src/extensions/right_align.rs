@@ -0,0 +1,72 @@
+use crate::ast::Node;
+use crate::html::{self, RenderHtml};
+use crate::markdown::{self, RenderMarkdown};
+use crate::{CosyParserExtension, ExtensionContext};
+
+/// Right-aligned text, written `[< text]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RightAligned {
+    /// The aligned content.
+    ///
+    /// Only the [`RightAlignExtension`] itself parses nested notations, see the
+    /// [module docs](crate::extensions).
+    pub nodes: Vec<Node<'static, RightAligned>>,
+}
+
+/// Parses `[< text]` into [`RightAligned`] text.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RightAlignExtension;
+
+impl CosyParserExtension for RightAlignExtension {
+    type Output = RightAligned;
+
+    fn parse_bracket(
+        &self,
+        content: &str,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        let body = content.strip_prefix("< ")?;
+        Some(RightAligned {
+            nodes: ctx.parse_inline(body),
+        })
+    }
+
+    fn parse_block(
+        &self,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        None
+    }
+}
+
+impl RenderHtml for RightAligned {
+    fn render_html(&self, out: &mut String) {
+        out.push_str("<span class=\"right-align\">");
+        out.push_str(&html::render_nodes(&self.nodes));
+        out.push_str("</span>");
+    }
+}
+
+impl RenderMarkdown for RightAligned {
+    fn render_markdown(&self, out: &mut String) {
+        out.push_str(&markdown::render_nodes(&self.nodes));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_right_aligned() {
+        let mut input_stream = "[< signed, [Alice]]";
+        let document = crate::parse(&mut input_stream, &RightAlignExtension).unwrap();
+        assert_eq!(
+            html::render(&document),
+            "<div class=\"line\"><span class=\"right-align\">signed, \
+             <a class=\"page-link\" href=\"./Alice\">Alice</a></span></div>\n"
+        );
+        assert_eq!(markdown::render(&document), "signed, [Alice](./Alice)\n");
+    }
+}
@@ -0,0 +1,145 @@
+use crate::html::{self, RenderHtml};
+use crate::markdown::RenderMarkdown;
+use crate::{CosyParserExtension, ExtensionContext};
+
+/// A ruby (furigana) annotation, written `[漢字(かんじ)]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ruby {
+    /// The annotated text, e.g., `漢字`.
+    pub base: String,
+    /// The reading, e.g., `かんじ`.
+    pub reading: String,
+}
+
+/// Parses `[base(reading)]` into a [`Ruby`] annotation.
+///
+/// Full-width parentheses are also accepted. The base must be kanji, optionally mixed with
+/// kana (e.g., `[送り仮名(おくりがな)]`), and the reading must be kana, so page links such as
+/// `[Rust (programming language)]` or `[東京(日本)]` stay links.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RubyExtension;
+
+impl CosyParserExtension for RubyExtension {
+    type Output = Ruby;
+
+    fn parse_bracket(
+        &self,
+        content: &str,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        let (base, reading) = split_ruby(content)?;
+        let is_base = base.chars().all(|c| is_kanji(c) || is_kana(c)) && base.chars().any(is_kanji);
+        let is_reading = reading.chars().all(is_kana);
+        (is_base && is_reading).then(|| Ruby {
+            base: base.to_string(),
+            reading: reading.to_string(),
+        })
+    }
+
+    fn parse_block(
+        &self,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        None
+    }
+}
+
+// Splits `base(reading)` with half- or full-width parentheses
+fn split_ruby(content: &str) -> Option<(&str, &str)> {
+    let (open, rest) = if let Some(rest) = content.strip_suffix(')') {
+        ('(', rest)
+    } else {
+        ('（', content.strip_suffix('）')?)
+    };
+    let (base, reading) = rest.split_once(open)?;
+    (!base.is_empty() && !reading.is_empty()).then_some((base, reading))
+}
+
+fn is_kanji(c: char) -> bool {
+    matches!(c,
+        '\u{3400}'..='\u{4dbf}'   // CJK Unified Ideographs Extension A
+        | '\u{4e00}'..='\u{9fff}' // CJK Unified Ideographs
+        | '\u{f900}'..='\u{faff}' // CJK Compatibility Ideographs
+        | '\u{20000}'..='\u{2fa1f}'
+        | '々' | '〆' | 'ヶ')
+}
+
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{309f}' | '\u{30a0}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}')
+}
+
+impl RenderHtml for Ruby {
+    fn render_html(&self, out: &mut String) {
+        out.push_str("<ruby>");
+        out.push_str(&html::escape(&self.base));
+        out.push_str("<rp>(</rp><rt>");
+        out.push_str(&html::escape(&self.reading));
+        out.push_str("</rt><rp>)</rp></ruby>");
+    }
+}
+
+// Markdown has no ruby syntax, but allows inline HTML
+impl RenderMarkdown for Ruby {
+    fn render_markdown(&self, out: &mut String) {
+        self.render_html(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::*;
+
+    fn parse(input: &str) -> Vec<Node<'_, Ruby>> {
+        let mut input_stream = input;
+        let mut document = crate::parse(&mut input_stream, &RubyExtension).unwrap();
+        match document.remove(0).content {
+            BlockContent::Line(nodes) => nodes,
+            content => panic!("unexpected block: {content:?}"),
+        }
+    }
+
+    fn ruby(base: &str, reading: &str) -> Node<'static, Ruby> {
+        Node::Custom(Ruby {
+            base: base.to_string(),
+            reading: reading.to_string(),
+        })
+    }
+
+    #[test]
+    fn parse_ruby() {
+        assert_eq!(parse("[漢字(かんじ)]"), vec![ruby("漢字", "かんじ")]);
+        assert_eq!(
+            parse("[送り仮名（おくりがな）]"),
+            vec![ruby("送り仮名", "おくりがな")]
+        );
+        assert_eq!(
+            parse("[東京(トウキョウ)]"),
+            vec![ruby("東京", "トウキョウ")]
+        );
+    }
+
+    #[test]
+    fn page_links_with_parentheses() {
+        for input in [
+            "[Rust (programming language)]",
+            "[東京(日本)]",
+            "[ひらがな(かな)]",
+            "[漢字(kanji)]",
+            "[漢字 (かんじ)]",
+            "[(かんじ)]",
+        ] {
+            let nodes = parse(input);
+            assert!(matches!(nodes[..], [Node::Link(_)]), "{input}: {nodes:?}");
+        }
+    }
+
+    #[test]
+    fn render_ruby() {
+        assert_eq!(
+            html::render_nodes(&parse("[漢字(かんじ)]を読む")),
+            "<ruby>漢字<rp>(</rp><rt>かんじ</rt><rp>)</rp></ruby>を読む"
+        );
+    }
+}
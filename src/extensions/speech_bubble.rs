@@ -0,0 +1,117 @@
+use crate::ast::Node;
+use crate::html::{self, RenderHtml};
+use crate::markdown::{self, RenderMarkdown};
+use crate::{CosyParserExtension, ExtensionContext};
+
+/// A speech bubble, written `[{ text]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeechBubble {
+    /// The content of the bubble.
+    ///
+    /// Only the [`SpeechBubbleExtension`] itself parses nested notations, see the
+    /// [module docs](crate::extensions).
+    pub nodes: Vec<Node<'static, SpeechBubble>>,
+}
+
+/// Parses `[{ text]` into a [`SpeechBubble`].
+///
+/// The text is parsed as inline markup, and an empty bubble is reported as a diagnostic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpeechBubbleExtension;
+
+impl CosyParserExtension for SpeechBubbleExtension {
+    type Output = SpeechBubble;
+
+    fn parse_bracket(
+        &self,
+        content: &str,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        let body = content.strip_prefix("{ ")?;
+        if body.trim().is_empty() {
+            ctx.diagnostic("empty speech bubble");
+        }
+        Some(SpeechBubble {
+            nodes: ctx.parse_inline(body),
+        })
+    }
+
+    fn parse_block(
+        &self,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        None
+    }
+}
+
+impl RenderHtml for SpeechBubble {
+    fn render_html(&self, out: &mut String) {
+        out.push_str("<span class=\"speech-bubble\">");
+        out.push_str(&html::render_nodes(&self.nodes));
+        out.push_str("</span>");
+    }
+}
+
+impl RenderMarkdown for SpeechBubble {
+    fn render_markdown(&self, out: &mut String) {
+        out.push_str(&markdown::render_nodes(&self.nodes));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::*;
+
+    #[test]
+    fn parse_speech_bubble() {
+        let mut input_stream = "Cat[{ We're all [* mad] here.]\n[{ ]";
+        let (document, diagnostics) =
+            crate::parse_with_diagnostics(&mut input_stream, &SpeechBubbleExtension).unwrap();
+
+        let bubble = SpeechBubble {
+            nodes: vec![
+                Node::Text("We're all ".into()),
+                Node::Decoration {
+                    decos: "*".into(),
+                    nodes: vec![Node::Text("mad".into())],
+                },
+                Node::Text(" here.".into()),
+            ],
+        };
+        let nodes = vec![Node::Text("Cat".into()), Node::Custom(bubble)];
+        assert_eq!(document[0].content, BlockContent::Line(nodes.clone()));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 1);
+
+        assert_eq!(
+            html::render_nodes(&nodes),
+            "Cat<span class=\"speech-bubble\">We&#39;re all <strong>mad</strong> here.</span>"
+        );
+    }
+
+    #[test]
+    fn parse_nested_notations_with_the_bubble_extension_only() {
+        let mut input_stream = "[{ [< signed]]";
+        let extension = (
+            SpeechBubbleExtension,
+            crate::extensions::RightAlignExtension,
+        );
+        let document = crate::parse(&mut input_stream, &extension).unwrap();
+
+        let BlockContent::Line(nodes) = &document[0].content else {
+            panic!("expected a line");
+        };
+        let [Node::Custom(crate::Either::Left(bubble))] = &nodes[..] else {
+            panic!("expected a speech bubble, got {nodes:?}");
+        };
+        assert_eq!(
+            bubble.nodes,
+            vec![Node::Decoration {
+                decos: "<".into(),
+                nodes: vec![Node::Text("signed".into())],
+            }]
+        );
+    }
+}
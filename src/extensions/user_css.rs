@@ -0,0 +1,116 @@
+use crate::ast::Node;
+use crate::html::{self, RenderHtml};
+use crate::markdown::{self, RenderMarkdown};
+use crate::{CosyParserExtension, ExtensionContext};
+
+/// A decoration styled by the project's UserCSS, such as `[! text]` or `[# text]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserCssDecoration {
+    /// The decoration character.
+    pub mark: char,
+    /// The decorated content.
+    ///
+    /// Only the [`UserCssExtension`] itself parses nested notations, see the
+    /// [module docs](crate::extensions).
+    pub nodes: Vec<Node<'static, UserCssDecoration>>,
+}
+
+/// Parses single-character decorations left to UserCSS into [`UserCssDecoration`]s.
+///
+/// Without the extension, these are generic decorations. The HTML output is the same
+/// `<span class="deco-!">`, so existing UserCSS keeps working.
+#[derive(Debug, Clone)]
+pub struct UserCssExtension {
+    marks: String,
+}
+
+impl Default for UserCssExtension {
+    /// Handles `[! text]` and `[# text]`.
+    fn default() -> Self {
+        Self::new("!#")
+    }
+}
+
+impl UserCssExtension {
+    /// Handles decorations written with any of the characters in `marks`.
+    pub fn new(marks: &str) -> Self {
+        UserCssExtension {
+            marks: marks.to_string(),
+        }
+    }
+}
+
+impl CosyParserExtension for UserCssExtension {
+    type Output = UserCssDecoration;
+
+    fn parse_bracket(
+        &self,
+        content: &str,
+        ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        let mut chars = content.chars();
+        let mark = chars.next().filter(|&c| self.marks.contains(c))?;
+        let body = chars.as_str().strip_prefix(' ')?;
+        Some(UserCssDecoration {
+            mark,
+            nodes: ctx.parse_inline(body),
+        })
+    }
+
+    fn parse_block(
+        &self,
+        _content: &str,
+        _ctx: &ExtensionContext<'_, Self>,
+    ) -> Option<Self::Output> {
+        None
+    }
+}
+
+impl RenderHtml for UserCssDecoration {
+    fn render_html(&self, out: &mut String) {
+        let mark = html::escape(&self.mark.to_string());
+        out.push_str(&format!("<span class=\"deco-{mark}\">"));
+        out.push_str(&html::render_nodes(&self.nodes));
+        out.push_str("</span>");
+    }
+}
+
+impl RenderMarkdown for UserCssDecoration {
+    fn render_markdown(&self, out: &mut String) {
+        out.push_str(&markdown::render_nodes(&self.nodes));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::*;
+
+    #[test]
+    fn parse_user_css_decorations() {
+        let mut input_stream = "[! note] [# [tag]] [% other] [!! twice]";
+        let document = crate::parse(&mut input_stream, &UserCssExtension::default()).unwrap();
+
+        let BlockContent::Line(nodes) = &document[0].content else {
+            panic!("expected a line");
+        };
+        assert_eq!(
+            nodes[0],
+            Node::Custom(UserCssDecoration {
+                mark: '!',
+                nodes: vec![Node::Text("note".into())],
+            })
+        );
+        assert!(matches!(
+            nodes[2],
+            Node::Custom(UserCssDecoration { mark: '#', .. })
+        ));
+        assert!(matches!(nodes[4], Node::Decoration { .. }));
+        assert!(matches!(nodes[6], Node::Decoration { .. }));
+
+        assert_eq!(
+            html::render_nodes(&nodes[..1]),
+            "<span class=\"deco-!\">note</span>"
+        );
+    }
+}
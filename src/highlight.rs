@@ -0,0 +1,198 @@
+//! Syntax highlighting for code blocks (requires the `highlight` feature).
+//!
+//! Highlighting is based on [syntect](https://docs.rs/syntect) with its bundled syntaxes and
+//! themes, so it works offline. The language is resolved by [`CodeBlockMeta::resolve`], from
+//! the filetype of the code block or the extension of its filename (`code:example.rs`
+//! highlights as Rust).
+
+use crate::ast::CodeBlockMeta;
+use crate::html::escape;
+use std::ops::Range;
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Style, Theme, ThemeSet};
+use syntect::html::{ClassStyle, ClassedHTMLGenerator, css_for_theme_with_class_style};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+/// The prefix of the CSS classes in highlighted HTML.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+const DEFAULT_THEME: &str = "InspiredGitHub";
+
+/// A span of highlighted code.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StyledSpan {
+    /// The byte range of the span in the code.
+    pub range: Range<usize>,
+    /// The colors and font style of the span in the highlighter's theme.
+    pub style: Style,
+}
+
+/// A syntax highlighter holding the loaded syntaxes and a theme.
+///
+/// Loading syntaxes is expensive, so create one highlighter and reuse it, or use
+/// [`Highlighter::shared`].
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Highlighter {
+    /// Creates a highlighter with the bundled syntaxes and the default theme.
+    pub fn new() -> Self {
+        let mut themes = ThemeSet::load_defaults();
+        let theme = themes
+            .themes
+            .remove(DEFAULT_THEME)
+            .expect("default theme is bundled");
+        Self::with_theme(theme)
+    }
+
+    /// Creates a highlighter with the bundled syntaxes and the given theme.
+    pub fn with_theme(theme: Theme) -> Self {
+        Highlighter {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme,
+        }
+    }
+
+    /// Returns a lazily created highlighter shared across the process.
+    pub fn shared() -> &'static Highlighter {
+        static SHARED: OnceLock<Highlighter> = OnceLock::new();
+        SHARED.get_or_init(Highlighter::new)
+    }
+
+    /// Finds the syntax of a code block from its filetype or filename.
+    ///
+    /// The language is resolved with [`CodeBlockMeta::resolve`]. Files in languages unknown to
+    /// the resolver may still be found by their extension.
+    pub fn find_syntax(&self, meta: &CodeBlockMeta) -> Option<&SyntaxReference> {
+        let info = meta.resolve();
+
+        let by_language = info.language.and_then(|language| {
+            self.syntaxes
+                .find_syntax_by_token(language.as_str())
+                .or_else(|| {
+                    language
+                        .extensions()
+                        .iter()
+                        .find_map(|ext| self.syntaxes.find_syntax_by_extension(ext))
+                })
+        });
+        let by_extension = || {
+            info.filename
+                .as_deref()
+                .and_then(|name| name.rsplit_once('.'))
+                .and_then(|(_, ext)| self.syntaxes.find_syntax_by_extension(ext))
+        };
+        by_language.or_else(by_extension)
+    }
+
+    /// Highlights the code of a code block as HTML with CSS classes.
+    ///
+    /// The returned markup is meant to be placed inside `<pre><code>`. Use [`Highlighter::css`]
+    /// for the matching stylesheet. Code in an unknown language is escaped without
+    /// highlighting.
+    pub fn highlight_html(&self, meta: &CodeBlockMeta, code: &str) -> String {
+        let Some(syntax) = self.find_syntax(meta) else {
+            return escape(code);
+        };
+        let mut generator =
+            ClassedHTMLGenerator::new_with_class_style(syntax, &self.syntaxes, CLASS_STYLE);
+        for line in LinesWithEndings::from(code) {
+            if generator
+                .parse_html_for_line_which_includes_newline(line)
+                .is_err()
+            {
+                return escape(code);
+            }
+        }
+        generator.finalize()
+    }
+
+    /// Returns the stylesheet for the classes used by [`Highlighter::highlight_html`].
+    pub fn css(&self) -> String {
+        css_for_theme_with_class_style(&self.theme, CLASS_STYLE).unwrap_or_default()
+    }
+
+    /// Highlights the code of a code block as styled spans covering the whole code.
+    ///
+    /// Returns `None` if the language is unknown.
+    pub fn highlight_spans(&self, meta: &CodeBlockMeta, code: &str) -> Option<Vec<StyledSpan>> {
+        let syntax = self.find_syntax(meta)?;
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+        let mut spans = Vec::new();
+        let mut offset = 0;
+        for line in LinesWithEndings::from(code) {
+            for (style, text) in highlighter.highlight_line(line, &self.syntaxes).ok()? {
+                spans.push(StyledSpan {
+                    range: offset..offset + text.len(),
+                    style,
+                });
+                offset += text.len();
+            }
+        }
+        Some(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_syntax_from_meta() {
+        let highlighter = Highlighter::shared();
+        let name = |meta: CodeBlockMeta| highlighter.find_syntax(&meta).map(|s| s.name.clone());
+
+        assert_eq!(
+            name(CodeBlockMeta::Either("example.rs".to_string())),
+            Some("Rust".to_string())
+        );
+        assert_eq!(
+            name(CodeBlockMeta::Either("python".to_string())),
+            Some("Python".to_string())
+        );
+        assert_eq!(
+            name(CodeBlockMeta::Both {
+                filename: "build".to_string(),
+                filetype: "sh".to_string(),
+            }),
+            Some("Bourne Again Shell (bash)".to_string())
+        );
+        assert_eq!(name(CodeBlockMeta::None), None);
+        assert_eq!(name(CodeBlockMeta::Either("notes".to_string())), None);
+    }
+
+    #[test]
+    fn highlight_to_html() {
+        let html = Highlighter::shared().highlight_html(
+            &CodeBlockMeta::Either("main.rs".to_string()),
+            "fn main() {}",
+        );
+        assert!(html.contains("<span class=\"hl-storage hl-type hl-function hl-rust\">fn</span>"));
+    }
+
+    #[test]
+    fn highlight_unknown_language_is_escaped() {
+        let html = Highlighter::shared().highlight_html(&CodeBlockMeta::None, "a < b");
+        assert_eq!(html, "a &lt; b");
+    }
+
+    #[test]
+    fn highlight_spans_cover_code() {
+        let code = "let x = 1;\nlet y = 2;";
+        let spans = Highlighter::shared()
+            .highlight_spans(&CodeBlockMeta::Either("js".to_string()), code)
+            .unwrap();
+        assert_eq!(spans.first().unwrap().range.start, 0);
+        assert_eq!(spans.last().unwrap().range.end, code.len());
+        assert!(spans.windows(2).all(|w| w[0].range.end == w[1].range.start));
+    }
+}
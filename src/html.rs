@@ -0,0 +1,585 @@
+//! HTML rendering of parsed documents.
+//!
+//! Indented blocks are rendered as nested lists, as Cosense does. Custom nodes produced by an
+//! extension are rendered through the [`RenderHtml`] trait. With the `highlight` feature, code
+//! blocks are syntax highlighted (see [`crate::highlight`]), and with the `mathml` feature,
+//! math is rendered as MathML (see [`crate::mathml`]).
+
+use crate::Either;
+use crate::ast::{Block, BlockContent, CodeBlockMeta, Document, Grouped, Link, Node, group_quotes};
+#[cfg(feature = "highlight")]
+use crate::highlight::Highlighter;
+use crate::links::page_path;
+use crate::url::{UrlKind, openstreetmap_url};
+use std::fmt::Write;
+use url::form_urlencoded::byte_serialize;
+
+// The URL schemes that are rendered as links
+const SAFE_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Renders a custom extension node to HTML.
+pub trait RenderHtml {
+    /// Appends the HTML representation of `self` to `out`.
+    fn render_html(&self, out: &mut String);
+}
+
+impl RenderHtml for () {
+    fn render_html(&self, _out: &mut String) {}
+}
+
+impl<L: RenderHtml, R: RenderHtml> RenderHtml for Either<L, R> {
+    fn render_html(&self, out: &mut String) {
+        match self {
+            Either::Left(left) => left.render_html(out),
+            Either::Right(right) => right.render_html(out),
+        }
+    }
+}
+
+/// Renders a whole document to an HTML fragment.
+pub fn render<T: RenderHtml>(document: &Document<T>) -> String {
+    let mut out = String::new();
+    let mut lists = ListStack::default();
+
+    for item in group_quotes(document) {
+        let kind = match &item {
+            Grouped::Block(Block {
+                content: BlockContent::NumberedLine { number, .. },
+                ..
+            }) => ListKind::Ordered(*number),
+            _ => ListKind::Bullet,
+        };
+        lists.enter(item.indent(), kind, &mut out);
+        match &item {
+            Grouped::Block(block) => render_block(block, &mut out),
+            Grouped::Quote(group) => push_quote(&group.lines, &mut out),
+        }
+        if item.indent() == 0 {
+            out.push('\n');
+        }
+    }
+    lists.enter(0, ListKind::Bullet, &mut out);
+
+    out
+}
+
+/// Renders a sequence of inline nodes to HTML.
+pub fn render_nodes<T: RenderHtml>(nodes: &[Node<T>]) -> String {
+    let mut out = String::new();
+    push_nodes(nodes, &mut out);
+    out
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ListKind {
+    Bullet,
+    Ordered(usize),
+}
+
+impl ListKind {
+    fn tag(self) -> &'static str {
+        match self {
+            ListKind::Bullet => "ul",
+            ListKind::Ordered(_) => "ol",
+        }
+    }
+}
+
+// Tracks the nested lists opened for indented blocks
+#[derive(Default)]
+struct ListStack {
+    tags: Vec<&'static str>,
+}
+
+impl ListStack {
+    // Moves to a list item of `kind` at `indent`, opening and closing lists as needed
+    fn enter(&mut self, indent: usize, kind: ListKind, out: &mut String) {
+        while self.tags.len() > indent {
+            self.close(out);
+        }
+        if indent == 0 {
+            if !out.is_empty() && !out.ends_with('\n') {
+                out.push('\n');
+            }
+            return;
+        }
+
+        // Switching between bullets and numbers at the same level starts a new list
+        if self.tags.len() == indent {
+            if self.tags.last() == Some(&kind.tag()) {
+                out.push_str("</li>");
+            } else {
+                self.close(out);
+            }
+        }
+        while self.tags.len() < indent - 1 {
+            out.push_str("<ul><li>");
+            self.tags.push("ul");
+        }
+        if self.tags.len() < indent {
+            let _ = write!(out, "<{}>", kind.tag());
+            self.tags.push(kind.tag());
+        }
+        match kind {
+            ListKind::Bullet => out.push_str("<li>"),
+            ListKind::Ordered(number) => {
+                let _ = write!(out, "<li value=\"{number}\">");
+            }
+        }
+    }
+
+    fn close(&mut self, out: &mut String) {
+        if let Some(tag) = self.tags.pop() {
+            let _ = write!(out, "</li></{tag}>");
+        }
+    }
+}
+
+fn render_block<T: RenderHtml>(block: &Block<T>, out: &mut String) {
+    match &block.content {
+        BlockContent::Line(nodes) => {
+            if block.indent == 0 {
+                out.push_str("<div class=\"line\">");
+                push_nodes(nodes, out);
+                out.push_str("</div>");
+            } else {
+                push_nodes(nodes, out);
+            }
+        }
+        BlockContent::NumberedLine { nodes, .. } => push_nodes(nodes, out),
+        BlockContent::CodeBlock { meta, content, .. } => {
+            out.push_str("<div class=\"code-block\">");
+            let title = match meta {
+                CodeBlockMeta::None => None,
+                CodeBlockMeta::Either(name) => Some(name),
+                CodeBlockMeta::Both { filename, .. } => Some(filename),
+            };
+            if let Some(title) = title {
+                out.push_str("<span class=\"code-block-title\">");
+                push_escaped(title, out);
+                out.push_str("</span>");
+            }
+            match meta.resolve().language {
+                Some(language) => {
+                    let _ = write!(out, "<pre><code class=\"language-{language}\">");
+                }
+                None => out.push_str("<pre><code>"),
+            }
+            push_code(meta, content, out);
+            out.push_str("</code></pre></div>");
+        }
+        BlockContent::Table {
+            name, header, rows, ..
+        } => {
+            out.push_str("<table class=\"table-block\">");
+            if !name.is_empty() {
+                out.push_str("<caption>");
+                push_escaped(name, out);
+                out.push_str("</caption>");
+            }
+            if !header.is_empty() {
+                out.push_str("<thead><tr>");
+                for cell in header {
+                    out.push_str("<th>");
+                    push_nodes(&cell.nodes, out);
+                    out.push_str("</th>");
+                }
+                out.push_str("</tr></thead>");
+            }
+            out.push_str("<tbody>");
+            for row in rows {
+                out.push_str("<tr>");
+                for cell in row {
+                    out.push_str("<td>");
+                    push_nodes(&cell.nodes, out);
+                    out.push_str("</td>");
+                }
+                out.push_str("</tr>");
+            }
+            out.push_str("</tbody></table>");
+        }
+        BlockContent::Quote(nodes) => push_quote(&[nodes], out),
+        BlockContent::Custom(custom) => custom.render_html(out),
+    }
+}
+
+#[cfg(feature = "highlight")]
+fn push_code(meta: &CodeBlockMeta, code: &str, out: &mut String) {
+    out.push_str(&Highlighter::shared().highlight_html(meta, code));
+}
+
+#[cfg(not(feature = "highlight"))]
+fn push_code(_meta: &CodeBlockMeta, code: &str, out: &mut String) {
+    push_escaped(code, out);
+}
+
+#[cfg(feature = "mathml")]
+fn push_math(tex: &str, out: &mut String) {
+    out.push_str(&crate::mathml::render(tex));
+}
+
+#[cfg(not(feature = "mathml"))]
+fn push_math(tex: &str, out: &mut String) {
+    out.push_str("<span class=\"math\">");
+    push_escaped(tex, out);
+    out.push_str("</span>");
+}
+
+// Quote lines of a paragraph are rendered in a single <blockquote>
+fn push_quote<T: RenderHtml>(lines: &[&[Node<T>]], out: &mut String) {
+    out.push_str("<blockquote>");
+    for (i, nodes) in lines.iter().enumerate() {
+        if i > 0 {
+            out.push_str("<br>");
+        }
+        push_nodes(nodes, out);
+    }
+    out.push_str("</blockquote>");
+}
+
+fn push_nodes<T: RenderHtml>(nodes: &[Node<T>], out: &mut String) {
+    for node in nodes {
+        push_node(node, out);
+    }
+}
+
+fn push_node<T: RenderHtml>(node: &Node<T>, out: &mut String) {
+    match node {
+        Node::Text(text) => push_escaped(text, out),
+        Node::Link(link) => push_link(link, out),
+        Node::Image { src, href } => match href {
+            Some(href) => push_anchor(href, "link", out, |out| push_media("img", src, out)),
+            None => push_media("img", src, out),
+        },
+        Node::Embed { url, kind } => push_embed(url, kind, out),
+        Node::Icon { name, count } => {
+            for _ in 0..*count {
+                out.push_str("<img class=\"icon\" alt=\"");
+                push_escaped(name, out);
+                out.push_str("\" src=\"");
+                let _ = write!(out, "./{}", escape(&page_path(name)));
+                out.push_str("/icon\">");
+            }
+        }
+        Node::Location {
+            lat,
+            lon,
+            zoom,
+            label,
+        } => {
+            let href = openstreetmap_url(*lat, *lon, *zoom);
+            push_anchor(&href, "location", out, |out| match label {
+                Some(label) => push_escaped(label, out),
+                None => {
+                    let _ = write!(out, "{lat},{lon}");
+                }
+            });
+        }
+        Node::InlineCode(code) => {
+            out.push_str("<code>");
+            push_escaped(code, out);
+            out.push_str("</code>");
+        }
+        Node::Math(tex) => push_math(tex, out),
+        Node::Decoration { decos, nodes } => {
+            let tags = decoration_tags(decos);
+            for (tag, class) in &tags {
+                match class {
+                    Some(class) => {
+                        let _ = write!(out, "<{tag} class=\"{class}\">");
+                    }
+                    None => {
+                        let _ = write!(out, "<{tag}>");
+                    }
+                }
+            }
+            push_nodes(nodes, out);
+            for (tag, _) in tags.iter().rev() {
+                let _ = write!(out, "</{tag}>");
+            }
+        }
+        Node::Custom(custom) => custom.render_html(out),
+    }
+}
+
+fn push_link<T: RenderHtml>(link: &Link<T>, out: &mut String) {
+    match link {
+        Link::Page(name) => {
+            let href = format!("./{}", page_path(name));
+            push_anchor(&href, "page-link", out, |out| push_escaped(name, out));
+        }
+        Link::Anchor { page, anchor } => {
+            let href = format!("./{}#{}", page_path(page), escape_fragment(anchor));
+            push_anchor(&href, "page-link", out, |out| {
+                push_escaped(page, out);
+                out.push('#');
+                push_escaped(anchor, out);
+            });
+        }
+        Link::Url(url) => push_anchor(url, "link", out, |out| push_escaped(url, out)),
+        Link::WithLabel { href, label } => push_anchor(href, "link", out, |out| {
+            push_nodes(label, out);
+        }),
+    }
+}
+
+fn push_embed(url: &str, kind: &UrlKind, out: &mut String) {
+    let iframe_src = match kind {
+        UrlKind::Image => return push_media("img", url, out),
+        UrlKind::Video => return push_media("video", url, out),
+        UrlKind::Audio => return push_media("audio", url, out),
+        UrlKind::YouTube { id, start } => match start {
+            Some(start) => format!("https://www.youtube.com/embed/{id}?start={start}"),
+            None => format!("https://www.youtube.com/embed/{id}"),
+        },
+        UrlKind::Vimeo { id } => format!("https://player.vimeo.com/video/{id}"),
+        UrlKind::Spotify { kind, id } => format!("https://open.spotify.com/embed/{kind}/{id}"),
+        UrlKind::Twitter { .. } | UrlKind::Custom { .. } | UrlKind::Other => {
+            push_anchor(url, "link embed", out, |out| push_escaped(url, out));
+            return;
+        }
+    };
+
+    out.push_str("<iframe class=\"embed\" src=\"");
+    push_escaped(&iframe_src, out);
+    out.push_str("\" allowfullscreen></iframe>");
+}
+
+// Maps decoration characters to HTML tags with an optional class
+fn decoration_tags(decos: &str) -> Vec<(&'static str, Option<String>)> {
+    let mut tags = Vec::new();
+
+    let level = decos.chars().filter(|&c| c == '*').count();
+    if level == 1 {
+        tags.push(("strong", None));
+    } else if level > 1 {
+        tags.push(("strong", Some(format!("level-{level}"))));
+    }
+    if decos.contains('/') {
+        tags.push(("em", None));
+    }
+    if decos.contains('-') {
+        tags.push(("del", None));
+    }
+    if decos.contains('_') {
+        tags.push(("u", None));
+    }
+
+    // Other characters are left to UserCSS
+    let mut others: Vec<char> = decos
+        .chars()
+        .filter(|c| !matches!(c, '*' | '/' | '-' | '_'))
+        .collect();
+    others.sort_unstable();
+    others.dedup();
+    for c in others {
+        let class = format!("deco-{}", escape(&c.to_string()));
+        tags.push(("span", Some(class)));
+    }
+
+    tags
+}
+
+fn escape_fragment(fragment: &str) -> String {
+    byte_serialize(fragment.as_bytes()).collect()
+}
+
+// Link what `content` writes to `href`, leaving it unlinked if the URL is not safe
+fn push_anchor(href: &str, class: &str, out: &mut String, content: impl FnOnce(&mut String)) {
+    let safe = is_safe_url(href);
+    if safe {
+        let _ = write!(out, "<a class=\"{class}\" href=\"");
+        push_escaped(href, out);
+        out.push_str("\">");
+    }
+    content(out);
+    if safe {
+        out.push_str("</a>");
+    }
+}
+
+// An image, video or audio element, or the URL as text if it is not safe
+fn push_media(tag: &str, src: &str, out: &mut String) {
+    if !is_safe_url(src) {
+        push_escaped(src, out);
+        return;
+    }
+    match tag {
+        "img" => out.push_str("<img class=\"image\" src=\""),
+        _ => {
+            let _ = write!(out, "<{tag} class=\"{tag}\" controls src=\"");
+        }
+    }
+    push_escaped(src, out);
+    match tag {
+        "img" => out.push_str("\">"),
+        _ => {
+            let _ = write!(out, "\"></{tag}>");
+        }
+    }
+}
+
+// Whether a URL may be linked to: http, https, mailto or relative. Other schemes (e.g.,
+// `javascript:`) could run script in the rendered page
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore leading control characters and spaces, and tabs and newlines anywhere
+    let url: String = url
+        .trim_start_matches(|c: char| c <= ' ')
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => SAFE_SCHEMES
+            .iter()
+            .any(|scheme| url[..i].eq_ignore_ascii_case(scheme)),
+        _ => true,
+    }
+}
+
+fn push_escaped(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+pub(crate) fn escape(s: &str) -> String {
+    let mut out = String::new();
+    push_escaped(s, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_str(input: &str) -> String {
+        let mut input_stream = input;
+        let document = crate::parse(&mut input_stream, &()).unwrap();
+        render(&document)
+    }
+
+    #[test]
+    fn render_inline_nodes() {
+        assert_eq!(
+            render_str("[* bold] <and> [Page Name] [https://example.com label]"),
+            "<div class=\"line\"><strong>bold</strong> &lt;and&gt; \
+             <a class=\"page-link\" href=\"./Page_Name\">Page Name</a> \
+             <a class=\"link\" href=\"https://example.com\">label</a></div>\n"
+        );
+    }
+
+    #[test]
+    fn render_unsafe_urls_as_text() {
+        assert_eq!(
+            render_str("[javascript:alert(1)] [click JavaScript:alert(1)]"),
+            "<div class=\"line\">javascript:alert(1) click</div>\n"
+        );
+        assert_eq!(
+            render_str("[https://example.com/a.png javascript:alert(1)] [mailto:a@example.com]"),
+            "<div class=\"line\"><img class=\"image\" src=\"https://example.com/a.png\"> \
+             <a class=\"link\" href=\"mailto:a@example.com\">mailto:a@example.com</a></div>\n"
+        );
+        assert!(is_safe_url("./Page#a"));
+        assert!(is_safe_url("HTTPS://example.com/a:b"));
+        assert!(!is_safe_url("data:text/html,x"));
+        assert!(!is_safe_url("java\tscript:x"));
+        assert!(!is_safe_url("\u{1}vbscript:x"));
+    }
+
+    #[test]
+    fn render_repeated_decorations_once() {
+        assert_eq!(
+            render_str("[!*! x]"),
+            "<div class=\"line\"><strong><span class=\"deco-!\">x</span></strong></div>\n"
+        );
+    }
+
+    #[test]
+    fn render_location() {
+        assert_eq!(
+            render_nodes::<()>(&[Node::Location {
+                lat: 35.68,
+                lon: 139.76,
+                zoom: Some(14),
+                label: Some("Tokyo".into()),
+            }]),
+            "<a class=\"location\" \
+             href=\"https://www.openstreetmap.org/?mlat=35.68&amp;mlon=139.76#map=14/35.68/139.76\">\
+             Tokyo</a>"
+        );
+    }
+
+    #[test]
+    fn render_embed() {
+        assert_eq!(
+            render_str("[https://youtu.be/dQw4w9WgXcQ?t=42]"),
+            "<div class=\"line\"><iframe class=\"embed\" \
+             src=\"https://www.youtube.com/embed/dQw4w9WgXcQ?start=42\" allowfullscreen>\
+             </iframe></div>\n"
+        );
+    }
+
+    #[test]
+    fn render_numbered_lists() {
+        assert_eq!(
+            render_str(" 1. one\n 2. two\n  bullet\n bullet"),
+            "<ol><li value=\"1\">one</li><li value=\"2\">two<ul><li>bullet</li></ul></li></ol>\
+             <ul><li>bullet</li></ul>\n"
+        );
+    }
+
+    #[test]
+    fn render_quote_groups() {
+        assert_eq!(
+            render_str("> a\n> b\nc\n > d\n > e"),
+            "<blockquote>a<br>b</blockquote>\n\
+             <div class=\"line\">c</div>\n\
+             <ul><li><blockquote>d<br>e</blockquote></li></ul>\n"
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "highlight"))]
+    fn render_code_block() {
+        assert_eq!(
+            render_str("code:hello.py\n print('<hi>')"),
+            "<div class=\"code-block\"><span class=\"code-block-title\">hello.py</span>\
+             <pre><code class=\"language-python\">print(&#39;&lt;hi&gt;&#39;)</code></pre></div>\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "mathml")]
+    fn render_math_as_mathml() {
+        let html = render_str("[$ x^2] [$ \\unknown]");
+        assert!(html.contains("<msup><mi>x</mi><mn>2</mn></msup>"));
+        assert!(html.contains("<span class=\"math\">\\unknown</span>"));
+    }
+
+    #[test]
+    fn render_table() {
+        assert_eq!(
+            render_str("table:t\n a\tb\n 1"),
+            "<table class=\"table-block\"><caption>t</caption>\
+             <thead><tr><th>a</th><th>b</th></tr></thead>\
+             <tbody><tr><td>1</td><td></td></tr></tbody></table>\n"
+        );
+    }
+
+    #[test]
+    fn render_nested_lists() {
+        assert_eq!(
+            render_str("root\n a\n  b\n c\nend"),
+            "<div class=\"line\">root</div>\n\
+             <ul><li>a<ul><li>b</li></ul></li><li>c</li></ul>\n\
+             <div class=\"line\">end</div>\n"
+        );
+    }
+}
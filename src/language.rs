@@ -0,0 +1,258 @@
+//! Language resolution for code blocks.
+//!
+//! `code:` headers mix filenames (`code:example.rs`) and language names (`code:python`), and
+//! [`CodeBlockMeta::Either`] cannot tell them apart. [`CodeBlockMeta::resolve`] sorts this out
+//! with a table of known languages, their aliases and their file extensions, which exporters
+//! and highlighters share.
+
+use crate::ast::CodeBlockMeta;
+use std::fmt;
+
+/// A known programming or markup language.
+///
+/// The id is the canonical lowercase name of the language (e.g., `"javascript"`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LanguageId(&'static str);
+
+/// The filename and language resolved from a code block header.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CodeInfo {
+    /// The filename, if the header names a file.
+    pub filename: Option<String>,
+    /// The language, if it is known.
+    pub language: Option<LanguageId>,
+}
+
+struct Language {
+    id: &'static str,
+    aliases: &'static [&'static str],
+    extensions: &'static [&'static str],
+    filenames: &'static [&'static str],
+}
+
+const fn lang(
+    id: &'static str,
+    aliases: &'static [&'static str],
+    extensions: &'static [&'static str],
+) -> Language {
+    Language {
+        id,
+        aliases,
+        extensions,
+        filenames: &[],
+    }
+}
+
+const LANGUAGES: &[Language] = &[
+    lang("c", &[], &["c", "h"]),
+    lang("clojure", &["clj"], &["clj", "cljs", "cljc", "edn"]),
+    lang(
+        "cpp",
+        &["c++", "cxx", "hpp"],
+        &["cpp", "cc", "cxx", "hpp", "hh", "hxx"],
+    ),
+    lang("csharp", &["c#", "cs"], &["cs"]),
+    lang("css", &[], &["css"]),
+    lang("dart", &[], &["dart"]),
+    lang("diff", &["patch"], &["diff", "patch"]),
+    Language {
+        id: "dockerfile",
+        aliases: &["docker"],
+        extensions: &["dockerfile"],
+        filenames: &["Dockerfile"],
+    },
+    lang("elixir", &["ex", "exs"], &["ex", "exs"]),
+    lang("erlang", &["erl"], &["erl", "hrl"]),
+    lang("go", &["golang"], &["go"]),
+    lang("haskell", &["hs"], &["hs"]),
+    lang("html", &["htm", "xhtml"], &["html", "htm", "xhtml"]),
+    lang("java", &[], &["java"]),
+    lang(
+        "javascript",
+        &["js", "node", "jsx"],
+        &["js", "mjs", "cjs", "jsx"],
+    ),
+    lang("json", &[], &["json"]),
+    lang("kotlin", &["kt"], &["kt", "kts"]),
+    lang("latex", &["tex"], &["tex"]),
+    lang("lua", &[], &["lua"]),
+    Language {
+        id: "makefile",
+        aliases: &["make", "mk"],
+        extensions: &["mk", "mak"],
+        filenames: &["Makefile", "GNUmakefile"],
+    },
+    lang("markdown", &["md"], &["md", "markdown"]),
+    lang("nix", &[], &["nix"]),
+    lang("objectivec", &["objc", "objective-c"], &["m"]),
+    lang("ocaml", &["ml"], &["ml", "mli"]),
+    lang("perl", &["pl"], &["pl", "pm"]),
+    lang("php", &[], &["php"]),
+    lang("powershell", &["ps1", "pwsh"], &["ps1"]),
+    lang("python", &["py", "python3"], &["py", "pyw"]),
+    lang("r", &[], &["r"]),
+    Language {
+        id: "ruby",
+        aliases: &["rb"],
+        extensions: &["rb", "rake", "gemspec"],
+        filenames: &["Gemfile", "Rakefile"],
+    },
+    lang("rust", &["rs"], &["rs"]),
+    lang("scala", &[], &["scala", "sc"]),
+    lang("scss", &["sass"], &["scss", "sass"]),
+    lang(
+        "shell",
+        &["sh", "bash", "zsh", "shellscript"],
+        &["sh", "bash", "zsh"],
+    ),
+    lang("sql", &[], &["sql"]),
+    lang("swift", &[], &["swift"]),
+    lang("toml", &[], &["toml"]),
+    lang("typescript", &["ts", "tsx"], &["ts", "mts", "cts", "tsx"]),
+    lang("vim", &["vimscript", "viml"], &["vim"]),
+    lang("xml", &["svg"], &["xml", "svg", "xsl"]),
+    lang("yaml", &["yml"], &["yaml", "yml"]),
+    lang("zig", &[], &["zig"]),
+];
+
+impl LanguageId {
+    /// Returns the canonical name of the language.
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+
+    /// Looks up a language by its canonical name or an alias (e.g., `js`), ignoring case.
+    pub fn from_name(name: &str) -> Option<LanguageId> {
+        let name = name.trim().to_lowercase();
+        LANGUAGES
+            .iter()
+            .find(|l| l.id == name || l.aliases.contains(&name.as_str()))
+            .map(|l| LanguageId(l.id))
+    }
+
+    /// Looks up a language by a file extension without the dot, ignoring case.
+    pub fn from_extension(ext: &str) -> Option<LanguageId> {
+        let ext = ext.to_lowercase();
+        LANGUAGES
+            .iter()
+            .find(|l| l.extensions.contains(&ext.as_str()))
+            .map(|l| LanguageId(l.id))
+    }
+
+    /// Looks up a language from a filename, by well-known names (e.g., `Makefile`) or by
+    /// extension.
+    pub fn from_filename(filename: &str) -> Option<LanguageId> {
+        let basename = filename.rsplit('/').next().unwrap_or(filename);
+        if let Some(l) = LANGUAGES.iter().find(|l| l.filenames.contains(&basename)) {
+            return Some(LanguageId(l.id));
+        }
+        let (_, ext) = basename.rsplit_once('.')?;
+        LanguageId::from_extension(ext)
+    }
+
+    /// Returns the file extensions of the language, most common first.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        LANGUAGES
+            .iter()
+            .find(|l| l.id == self.0)
+            .map_or(&[], |l| l.extensions)
+    }
+}
+
+fn is_known_filename(name: &str) -> bool {
+    LANGUAGES.iter().any(|l| l.filenames.contains(&name))
+}
+
+impl fmt::Display for LanguageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl CodeBlockMeta {
+    /// Resolves the filename and language of the code block.
+    ///
+    /// A single name is taken as a language if it is a known language name or alias, and as
+    /// a filename otherwise. An explicit filetype takes precedence over the extension of the
+    /// filename.
+    pub fn resolve(&self) -> CodeInfo {
+        match self {
+            CodeBlockMeta::None => CodeInfo::default(),
+            CodeBlockMeta::Either(name) => match LanguageId::from_name(name) {
+                // Well-known filenames such as `Makefile` are also language names
+                Some(language) if !is_known_filename(name) => CodeInfo {
+                    filename: None,
+                    language: Some(language),
+                },
+                _ => CodeInfo {
+                    filename: Some(name.clone()),
+                    language: LanguageId::from_filename(name),
+                },
+            },
+            CodeBlockMeta::Both { filename, filetype } => CodeInfo {
+                filename: Some(filename.clone()),
+                language: LanguageId::from_name(filetype)
+                    .or_else(|| LanguageId::from_extension(filetype))
+                    .or_else(|| LanguageId::from_filename(filename)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(meta: CodeBlockMeta) -> (Option<String>, Option<&'static str>) {
+        let info = meta.resolve();
+        (info.filename, info.language.map(|l| l.as_str()))
+    }
+
+    #[test]
+    fn resolve_filename() {
+        assert_eq!(
+            resolve(CodeBlockMeta::Either("example.rs".to_string())),
+            (Some("example.rs".to_string()), Some("rust"))
+        );
+        assert_eq!(
+            resolve(CodeBlockMeta::Either("Makefile".to_string())),
+            (Some("Makefile".to_string()), Some("makefile"))
+        );
+        assert_eq!(
+            resolve(CodeBlockMeta::Either("notes".to_string())),
+            (Some("notes".to_string()), None)
+        );
+    }
+
+    #[test]
+    fn resolve_language_name() {
+        assert_eq!(
+            resolve(CodeBlockMeta::Either("python".to_string())),
+            (None, Some("python"))
+        );
+        assert_eq!(
+            resolve(CodeBlockMeta::Either("JS".to_string())),
+            (None, Some("javascript"))
+        );
+    }
+
+    #[test]
+    fn resolve_both() {
+        assert_eq!(
+            resolve(CodeBlockMeta::Both {
+                filename: "script".to_string(),
+                filetype: "sh".to_string(),
+            }),
+            (Some("script".to_string()), Some("shell"))
+        );
+        // The filetype wins over the extension
+        assert_eq!(
+            resolve(CodeBlockMeta::Both {
+                filename: "page.html".to_string(),
+                filetype: "php".to_string(),
+            }),
+            (Some("page.html".to_string()), Some("php"))
+        );
+        assert_eq!(resolve(CodeBlockMeta::None), (None, None));
+    }
+}
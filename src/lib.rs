@@ -1,8 +1,30 @@
 pub mod ast;
+pub mod dynamic;
 mod extension;
+#[cfg(feature = "extensions")]
+pub mod extensions;
+#[cfg(feature = "highlight")]
+pub mod highlight;
+pub mod html;
+pub mod language;
+pub mod links;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod markdown;
+#[cfg(feature = "mathml")]
+pub mod mathml;
 mod parser;
+pub mod table;
 mod tokens;
-mod url;
+pub mod url;
 
-pub use extension::CosyParserExtension;
-pub use parser::parse;
+pub use extension::{
+    BracketKind, CosyParserExtension, Diagnostic, Either, ExtensionContext, InlineSpan,
+};
+#[cfg(feature = "rayon")]
+pub use parser::parse_pages;
+pub use parser::{
+    BlockIter, BlockReader, IncrementalDocument, PageResult, ReadError, Token, TokenKind, parse,
+    parse_page, parse_page_with_diagnostics, parse_with_diagnostics, tokenize,
+    tokenize_with_diagnostics,
+};
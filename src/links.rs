@@ -0,0 +1,179 @@
+//! Page link extraction and the link graph between pages.
+//!
+//! Cosense treats titles case-insensitively and spaces and underscores as equivalent, and a
+//! link to `Page#anchor` points to the same page as `Page`. [`normalize_title`] applies these
+//! rules so backlinks can be computed by comparing normalized titles.
+
+use crate::ast::{Block, BlockContent, Document, Link, Node};
+use std::collections::{BTreeMap, BTreeSet};
+use url::form_urlencoded::byte_serialize;
+
+/// Splits `Page#anchor` into the page name and the anchor.
+///
+/// The anchor must be non-empty and free of whitespace, so that page names such as `C#` or
+/// `C# tutorial` are left intact.
+pub fn split_anchor(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once('#') {
+        Some((page, anchor))
+            if !page.is_empty()
+                && !anchor.is_empty()
+                && !anchor.chars().any(char::is_whitespace) =>
+        {
+            (page, Some(anchor))
+        }
+        _ => (name, None),
+    }
+}
+
+/// Returns the URL path of a page, with spaces replaced by underscores as Cosense does.
+pub fn page_path(name: &str) -> String {
+    byte_serialize(name.replace(' ', "_").as_bytes()).collect()
+}
+
+/// Normalizes a page title for comparison.
+///
+/// The anchor is dropped, surrounding whitespace is trimmed, spaces become underscores and
+/// letters are lowercased.
+pub fn normalize_title(title: &str) -> String {
+    let (page, _) = split_anchor(title.trim());
+    page.trim().replace(' ', "_").to_lowercase()
+}
+
+/// Returns the names of the pages linked from a document, in order of appearance.
+///
+/// Anchored links (`[Page#anchor]`) yield only the page name.
+pub fn page_links<'a, T>(document: &'a Document<'_, T>) -> Vec<&'a str> {
+    let mut links = Vec::new();
+    for block in document {
+        collect_block(block, &mut links);
+    }
+    links
+}
+
+fn collect_block<'a, T>(block: &'a Block<T>, links: &mut Vec<&'a str>) {
+    match &block.content {
+        BlockContent::Line(nodes)
+        | BlockContent::NumberedLine { nodes, .. }
+        | BlockContent::Quote(nodes) => collect_nodes(nodes, links),
+        BlockContent::Table { header, rows, .. } => {
+            for cell in header.iter().chain(rows.iter().flatten()) {
+                collect_nodes(&cell.nodes, links);
+            }
+        }
+        BlockContent::CodeBlock { .. } | BlockContent::Custom(_) => {}
+    }
+}
+
+fn collect_nodes<'a, T>(nodes: &'a [Node<T>], links: &mut Vec<&'a str>) {
+    for node in nodes {
+        match node {
+            Node::Link(Link::Page(page)) | Node::Link(Link::Anchor { page, .. }) => {
+                links.push(page)
+            }
+            Node::Link(Link::WithLabel { label, .. }) => collect_nodes(label, links),
+            Node::Decoration { nodes, .. } => collect_nodes(nodes, links),
+            _ => {}
+        }
+    }
+}
+
+/// The graph of page links across a set of pages.
+#[derive(Debug, Default, Clone)]
+pub struct LinkGraph {
+    // Normalized title -> display title
+    titles: BTreeMap<String, String>,
+    // Normalized title -> normalized titles of the linked pages
+    outgoing: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl LinkGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a page and its outgoing links, replacing any previous entry with the same title.
+    pub fn add_page<T>(&mut self, title: &str, document: &Document<T>) {
+        let key = normalize_title(title);
+        let targets = page_links(document)
+            .into_iter()
+            .map(normalize_title)
+            .filter(|target| *target != key)
+            .collect();
+        self.titles.insert(key.clone(), title.to_string());
+        self.outgoing.insert(key, targets);
+    }
+
+    /// Removes a page and its outgoing links.
+    pub fn remove_page(&mut self, title: &str) {
+        let key = normalize_title(title);
+        self.titles.remove(&key);
+        self.outgoing.remove(&key);
+    }
+
+    /// Returns the normalized titles of the pages linked from `title`.
+    pub fn links_from(&self, title: &str) -> impl Iterator<Item = &str> {
+        self.outgoing
+            .get(&normalize_title(title))
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Returns the titles of the pages that link to `title`.
+    pub fn backlinks(&self, title: &str) -> Vec<&str> {
+        let key = normalize_title(title);
+        self.outgoing
+            .iter()
+            .filter(|(_, targets)| targets.contains(&key))
+            .filter_map(|(source, _)| self.titles.get(source).map(String::as_str))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Document<'_, ()> {
+        let mut input_stream = input;
+        crate::parse(&mut input_stream, &()).unwrap()
+    }
+
+    #[test]
+    fn test_split_anchor() {
+        assert_eq!(split_anchor("Page#abc"), ("Page", Some("abc")));
+        assert_eq!(split_anchor("C#"), ("C#", None));
+        assert_eq!(split_anchor("#abc"), ("#abc", None));
+        assert_eq!(split_anchor("C# tutorial"), ("C# tutorial", None));
+    }
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(normalize_title("Some Page"), "some_page");
+        assert_eq!(normalize_title("some_page#5f3a"), "some_page");
+        assert_eq!(normalize_title(" Some Page "), "some_page");
+    }
+
+    #[test]
+    fn test_page_links() {
+        let document = parse("[A] and [B#line]\n> [* bold] [C]\n code:x.rs\n  [D]");
+        assert_eq!(page_links(&document), vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_backlinks_ignore_anchors() {
+        let mut graph = LinkGraph::new();
+        graph.add_page("First", &parse("[Target Page#5f3a]"));
+        graph.add_page("Second", &parse("[target_page]"));
+        graph.add_page("Third", &parse("[Other]"));
+
+        assert_eq!(graph.backlinks("Target Page"), vec!["First", "Second"]);
+        assert_eq!(
+            graph.links_from("first").collect::<Vec<_>>(),
+            vec!["target_page"]
+        );
+
+        graph.remove_page("Second");
+        assert_eq!(graph.backlinks("Target Page"), vec!["First"]);
+    }
+}
@@ -0,0 +1,594 @@
+//! A language server for Cosense pages (requires the `lsp` feature).
+//!
+//! The server speaks the Language Server Protocol over any reader and writer; the `cosy-lsp`
+//! binary runs it over stdio. Page files (`.cosense` and `.txt`) are loaded from the workspace
+//! folders, and a page is identified by its first line, so `[Page]` resolves to the file whose
+//! title is `Page` regardless of the file name.
+//!
+//! It provides semantic tokens for decorations, links, code and math (from [`crate::tokenize`]),
+//! go-to-definition on page links, backlinks as references, completion of page titles after `[`,
+//! and diagnostics for unclosed brackets.
+
+mod transport;
+mod workspace;
+
+use crate::links::{normalize_title, split_anchor};
+use crate::tokens::ICON_SUFFIX;
+use crate::{Token, TokenKind};
+use lsp_types::notification::{self, Notification};
+use lsp_types::request::{self, Request};
+use lsp_types::*;
+use serde_json::{Value, json};
+use std::io::{self, BufRead, Write};
+use transport::{read_message, write_message};
+use workspace::{PageFile, Workspace, uri_to_path};
+
+// JSON-RPC error codes
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+// Indexed by `token_type`
+const TOKEN_TYPES: [SemanticTokenType; 7] = [
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::STRING,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::DECORATOR,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::OPERATOR,
+];
+
+// The semantic token type of a token kind; plain text is left to the editor
+fn token_type(kind: TokenKind) -> Option<u32> {
+    use TokenKind::*;
+    match kind {
+        BlockPrefix | BlockName | QuoteMark | ListNumber => Some(0),
+        CodeMark | Code => Some(1),
+        MathMark | Math => Some(2),
+        Decoration => Some(3),
+        PageLink | Icon => Some(4),
+        Url | Location => Some(5),
+        Bracket => Some(6),
+        Indent | Newline | Text | UnclosedBracket | Separator | DecoratedText | LinkLabel
+        | CellSeparator | Custom => None,
+    }
+}
+
+/// Runs a server until the client sends `exit` or closes the input.
+///
+/// Returns an error if reading or writing fails, or if a message is too long or not JSON.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::new();
+    while let Some(message) = read_message(&mut input)? {
+        for reply in server.handle(message) {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exited {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The state of a language server: the pages of the workspace and the open documents.
+#[derive(Default)]
+pub struct Server {
+    workspace: Workspace,
+    shutdown: bool,
+    exited: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles a JSON-RPC message and returns the messages to send back.
+    pub fn handle(&mut self, message: Value) -> Vec<Value> {
+        let Some(message) = message.as_object() else {
+            return vec![error(
+                Value::Null,
+                INVALID_REQUEST,
+                "invalid JSON-RPC message",
+            )];
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        match (
+            message.get("method").and_then(Value::as_str),
+            message.get("id"),
+        ) {
+            (Some(method), Some(id)) => vec![self.request(method, id.clone(), params)],
+            (Some(method), None) => self.notify(method, params),
+            // Responses to requests we never send
+            (None, _) => Vec::new(),
+        }
+    }
+
+    /// Returns whether the client has sent `exit`.
+    pub fn is_exited(&self) -> bool {
+        self.exited
+    }
+
+    fn request(&mut self, method: &str, id: Value, params: Value) -> Value {
+        if self.shutdown {
+            return error(id, INVALID_REQUEST, "the server is shutting down");
+        }
+
+        let result = match method {
+            request::Initialize::METHOD => {
+                call::<request::Initialize>(params, |params| self.initialize(params))
+            }
+            request::Shutdown::METHOD => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            request::SemanticTokensFullRequest::METHOD => {
+                call::<request::SemanticTokensFullRequest>(params, |params| {
+                    self.semantic_tokens(params)
+                })
+            }
+            request::GotoDefinition::METHOD => {
+                call::<request::GotoDefinition>(params, |params| self.definition(params))
+            }
+            request::References::METHOD => {
+                call::<request::References>(params, |params| self.references(params))
+            }
+            request::Completion::METHOD => {
+                call::<request::Completion>(params, |params| self.completion(params))
+            }
+            _ => return error(id, METHOD_NOT_FOUND, &format!("unknown method {method}")),
+        };
+
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error(id, INVALID_PARAMS, &e.to_string()),
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Vec<Value> {
+        let uri = match method {
+            notification::Exit::METHOD => {
+                self.exited = true;
+                return Vec::new();
+            }
+            notification::DidOpenTextDocument::METHOD => {
+                let Ok(params) = serde_json::from_value::<DidOpenTextDocumentParams>(params) else {
+                    return Vec::new();
+                };
+                let document = params.text_document;
+                self.workspace.update(document.uri.clone(), document.text);
+                document.uri
+            }
+            notification::DidChangeTextDocument::METHOD => {
+                let Ok(mut params) = serde_json::from_value::<DidChangeTextDocumentParams>(params)
+                else {
+                    return Vec::new();
+                };
+                // The server asks for full text synchronization
+                let Some(change) = params.content_changes.pop() else {
+                    return Vec::new();
+                };
+                let uri = params.text_document.uri;
+                self.workspace.update(uri.clone(), change.text);
+                uri
+            }
+            notification::DidCloseTextDocument::METHOD => {
+                let Ok(params) = serde_json::from_value::<DidCloseTextDocumentParams>(params)
+                else {
+                    return Vec::new();
+                };
+                let uri = params.text_document.uri;
+                self.workspace.reload(&uri);
+                // Diagnostics are only reported for open documents
+                return vec![publish_diagnostics(uri, Vec::new())];
+            }
+            _ => return Vec::new(),
+        };
+        vec![publish_diagnostics(uri.clone(), self.diagnostics(&uri))]
+    }
+
+    fn initialize(&mut self, params: InitializeParams) -> InitializeResult {
+        #[allow(deprecated)]
+        let roots = match params.workspace_folders {
+            Some(folders) => folders.into_iter().map(|folder| folder.uri).collect(),
+            None => params.root_uri.into_iter().collect::<Vec<_>>(),
+        };
+        for root in roots {
+            if let Some(path) = uri_to_path(&root) {
+                // An unreadable folder leaves the pages that were loaded
+                let _ = self.workspace.load(&path);
+            }
+        }
+
+        InitializeResult {
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
+                semantic_tokens_provider: Some(
+                    SemanticTokensServerCapabilities::SemanticTokensOptions(
+                        SemanticTokensOptions {
+                            legend: SemanticTokensLegend {
+                                token_types: TOKEN_TYPES.to_vec(),
+                                token_modifiers: Vec::new(),
+                            },
+                            full: Some(SemanticTokensFullOptions::Bool(true)),
+                            ..Default::default()
+                        },
+                    ),
+                ),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                completion_provider: Some(CompletionOptions {
+                    trigger_characters: Some(vec!["[".into()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            server_info: Some(ServerInfo {
+                name: "cosy-lsp".into(),
+                version: Some(env!("CARGO_PKG_VERSION").into()),
+            }),
+        }
+    }
+
+    fn semantic_tokens(&self, params: SemanticTokensParams) -> Option<SemanticTokensResult> {
+        let file = self.workspace.get(&params.text_document.uri)?;
+
+        let mut data = Vec::new();
+        let mut previous = Position::default();
+        for (line, token) in tokens(file) {
+            let Some(token_type) = token_type(token.kind) else {
+                continue;
+            };
+            let range = token_range(file, line, token);
+            let delta_start = if range.start.line == previous.line {
+                range.start.character - previous.character
+            } else {
+                range.start.character
+            };
+            data.push(SemanticToken {
+                delta_line: range.start.line - previous.line,
+                delta_start,
+                length: range.end.character - range.start.character,
+                token_type,
+                token_modifiers_bitset: 0,
+            });
+            previous = range.start;
+        }
+        Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data,
+        }))
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let file = self.workspace.get(&position.text_document.uri)?;
+        let target = link_at(file, position.position)?;
+        let (uri, _) = self.workspace.find(target)?;
+        Some(GotoDefinitionResponse::Scalar(Location {
+            uri: uri.clone(),
+            range: Range::default(),
+        }))
+    }
+
+    // The pages linking to the page under the cursor, or to the current page
+    fn references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let position = params.text_document_position;
+        let file = self.workspace.get(&position.text_document.uri)?;
+        let title = link_at(file, position.position).unwrap_or(&file.title);
+        let key = normalize_title(title);
+
+        let mut locations = Vec::new();
+        if params.context.include_declaration
+            && let Some((uri, _)) = self.workspace.find(title)
+        {
+            locations.push(Location {
+                uri: uri.clone(),
+                range: Range::default(),
+            });
+        }
+        for (uri, source) in self.workspace.backlinks(title) {
+            let links = tokens(source).filter(|(_, token)| {
+                target(source, token).is_some_and(|target| normalize_title(target) == key)
+            });
+            locations.extend(links.map(|(line, token)| Location {
+                uri: uri.clone(),
+                range: token_range(source, line, token),
+            }));
+        }
+        Some(locations)
+    }
+
+    // Page titles for an unclosed `[` before the cursor
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let position = params.text_document_position;
+        let file = self.workspace.get(&position.text_document.uri)?;
+        let line = file.text.split('\n').nth(position.position.line as usize)?;
+        let before = &line[..byte_offset(line, position.position.character)];
+        let open = before.rfind('[')?;
+        if before[open..].contains(']') {
+            return None;
+        }
+
+        let key = normalize_title(&before[open + 1..]);
+        let range = Range {
+            start: Position {
+                line: position.position.line,
+                character: utf16_len(&line[..open + 1]),
+            },
+            end: position.position,
+        };
+        let items = self
+            .workspace
+            .titles()
+            .filter(|title| normalize_title(title).contains(&key))
+            .map(|title| CompletionItem {
+                label: title.into(),
+                kind: Some(CompletionItemKind::REFERENCE),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range,
+                    new_text: title.into(),
+                })),
+                ..Default::default()
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn diagnostics(&self, uri: &Uri) -> Vec<Diagnostic> {
+        let Some(file) = self.workspace.get(uri) else {
+            return Vec::new();
+        };
+        tokens(file)
+            .filter(|(_, token)| token.kind == TokenKind::UnclosedBracket)
+            .map(|(line, token)| Diagnostic {
+                range: token_range(file, line, token),
+                severity: Some(DiagnosticSeverity::WARNING),
+                source: Some("cosy".into()),
+                message: "unclosed bracket".into(),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+fn call<R: Request>(
+    params: Value,
+    handler: impl FnOnce(R::Params) -> R::Result,
+) -> serde_json::Result<Value> {
+    serde_json::to_value(handler(serde_json::from_value(params)?))
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn publish_diagnostics(uri: Uri, diagnostics: Vec<Diagnostic>) -> Value {
+    let params = PublishDiagnosticsParams {
+        uri,
+        diagnostics,
+        version: None,
+    };
+    json!({
+        "jsonrpc": "2.0",
+        "method": notification::PublishDiagnostics::METHOD,
+        "params": params,
+    })
+}
+
+// The tokens of a page with their line numbers
+fn tokens(file: &PageFile) -> impl Iterator<Item = (usize, &Token)> {
+    file.tokens
+        .iter()
+        .enumerate()
+        .flat_map(|(line, tokens)| tokens.iter().map(move |token| (line, token)))
+}
+
+// The page a link or icon points to
+fn target<'a>(file: &'a PageFile, token: &Token) -> Option<&'a str> {
+    let text = &file.text[token.range.clone()];
+    match token.kind {
+        TokenKind::PageLink => Some(split_anchor(text).0),
+        TokenKind::Icon => Some(text.trim_end_matches(ICON_SUFFIX)),
+        _ => None,
+    }
+}
+
+// The page linked under the cursor, including on the brackets around the link
+fn link_at(file: &PageFile, position: Position) -> Option<&str> {
+    let tokens = file.tokens.get(position.line as usize)?;
+    let start = tokens.first()?.range.start;
+    let line = file.text[start..].split('\n').next()?;
+    let offset = start + byte_offset(line, position.character);
+    tokens
+        .iter()
+        .filter(|token| token.range.start <= offset + 1 && offset <= token.range.end)
+        .find_map(|token| target(file, token))
+}
+
+// Positions count UTF-16 code units from the start of the line
+fn token_range(file: &PageFile, line: usize, token: &Token) -> Range {
+    let start = file.tokens[line][0].range.start;
+    let position = |offset| Position {
+        line: line as u32,
+        character: utf16_len(&file.text[start..offset]),
+    };
+    Range {
+        start: position(token.range.start),
+        end: position(token.range.end),
+    }
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+fn byte_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= character {
+            return offset;
+        }
+        units += c.len_utf16() as u32;
+    }
+    line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(server: &mut Server, uri: &str, text: &str) -> Vec<Value> {
+        server.handle(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": { "uri": uri, "languageId": "cosense", "version": 1, "text": text }
+            }
+        }))
+    }
+
+    fn request(server: &mut Server, method: &str, params: Value) -> Value {
+        let mut replies = server.handle(json!({
+            "jsonrpc": "2.0", "id": 1, "method": method, "params": params
+        }));
+        assert_eq!(replies.len(), 1);
+        replies.remove(0)["result"].take()
+    }
+
+    fn position(uri: &str, line: u32, character: u32) -> Value {
+        json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character }
+        })
+    }
+
+    const HOME: &str = "file:///ws/home.cosense";
+    const GUIDE: &str = "file:///ws/guide.txt";
+
+    fn server() -> Server {
+        let mut server = Server::new();
+        open(&mut server, GUIDE, "Guide\nsee [* [Home]]\n`code` [$ x]");
+        open(
+            &mut server,
+            HOME,
+            "Home\n日本 [Guide] [Guide#a1b2]\n[Missing",
+        );
+        server
+    }
+
+    #[test]
+    fn report_unclosed_brackets() {
+        let mut server = Server::new();
+        let replies = open(&mut server, HOME, "Home\n[ok] [broken\n");
+        assert_eq!(replies[0]["method"], "textDocument/publishDiagnostics");
+        let diagnostics = &replies[0]["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({ "start": { "line": 1, "character": 5 }, "end": { "line": 1, "character": 6 } })
+        );
+    }
+
+    #[test]
+    fn semantic_tokens() {
+        let mut server = server();
+        let result = request(
+            &mut server,
+            "textDocument/semanticTokens/full",
+            json!({ "textDocument": { "uri": GUIDE } }),
+        );
+        // Brackets, decoration characters, the page name, inline code and math
+        assert_eq!(
+            result["data"],
+            json!([
+                1, 4, 1, 6, 0, 0, 1, 1, 3, 0, 0, 2, 1, 6, 0, 0, 1, 4, 4, 0, 0, 4, 1, 6, 0, 0, 1, 1,
+                6, 0, 1, 0, 1, 1, 0, 0, 1, 4, 1, 0, 0, 4, 1, 1, 0, 0, 2, 1, 6, 0, 0, 1, 1, 2, 0, 0,
+                1, 2, 2, 0, 0, 2, 1, 6, 0
+            ])
+        );
+    }
+
+    #[test]
+    fn go_to_definition() {
+        let mut server = server();
+        // UTF-16 positions after non-ASCII text
+        let result = request(&mut server, "textDocument/definition", position(HOME, 1, 4));
+        assert_eq!(result["uri"], GUIDE);
+        let result = request(&mut server, "textDocument/definition", position(HOME, 1, 0));
+        assert_eq!(result, Value::Null);
+    }
+
+    #[test]
+    fn find_references() {
+        let mut server = server();
+        let mut params = position(GUIDE, 0, 0);
+        params["context"] = json!({ "includeDeclaration": false });
+        let result = request(&mut server, "textDocument/references", params);
+        let lines: Vec<_> = result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|location| (location["uri"].clone(), location["range"]["start"].clone()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (json!(HOME), json!({ "line": 1, "character": 4 })),
+                (json!(HOME), json!({ "line": 1, "character": 12 })),
+            ]
+        );
+    }
+
+    #[test]
+    fn complete_page_titles() {
+        let mut server = server();
+        let result = request(&mut server, "textDocument/completion", position(HOME, 2, 4));
+        let labels: Vec<_> = result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].clone())
+            .collect();
+        assert!(labels.is_empty());
+
+        open(&mut server, HOME, "Home\n[gu");
+        let result = request(&mut server, "textDocument/completion", position(HOME, 1, 3));
+        assert_eq!(result[0]["label"], "Guide");
+        assert_eq!(
+            result[0]["textEdit"]["range"]["start"],
+            json!({ "line": 1, "character": 1 })
+        );
+    }
+
+    #[test]
+    fn run_over_streams() {
+        let mut input = Vec::new();
+        for message in [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "capabilities": {} } }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "unknown" }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ] {
+            write_message(&mut input, &message).unwrap();
+        }
+
+        let mut output = Vec::new();
+        run(input.as_slice(), &mut output).unwrap();
+
+        let mut output = output.as_slice();
+        let initialize = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(
+            initialize["result"]["capabilities"]["completionProvider"]["triggerCharacters"],
+            json!(["["])
+        );
+        let unknown = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+        let shutdown = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(shutdown["id"], 3);
+        assert!(read_message(&mut output).unwrap().is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+// The longest message body accepted, so that a bad header cannot exhaust memory
+const MAX_LENGTH: usize = 64 << 20;
+
+// Read a message framed by a `Content-Length` header. Returns `None` at the end of the input,
+// and an error for a body that is too long or not JSON
+pub(crate) fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing Content-Length header",
+        ));
+    };
+    if length > MAX_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {length} bytes exceeds the limit of {MAX_LENGTH}"),
+        ));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+pub(crate) fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_invalid_messages() {
+        let mut input = "Content-Length: 99999999999\r\n\r\n{}".as_bytes();
+        let error = read_message(&mut input).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut input = "Content-Length: 3\r\n\r\n{x}".as_bytes();
+        let error = read_message(&mut input).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut input = "Content-Length: 2\r\n\r\n{}".as_bytes();
+        assert_eq!(
+            read_message(&mut input).unwrap(),
+            Some(serde_json::json!({}))
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use crate::Token;
+use crate::links::{LinkGraph, normalize_title};
+use lsp_types::Uri;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{fs, io};
+
+// The extensions of page files
+const EXTENSIONS: [&str; 2] = ["cosense", "txt"];
+
+pub(crate) struct PageFile {
+    pub title: String,
+    pub text: String,
+    pub tokens: Vec<Vec<Token>>,
+}
+
+// The pages of the workspace, by URI, and the links between them
+#[derive(Default)]
+pub(crate) struct Workspace {
+    pages: BTreeMap<Uri, PageFile>,
+    graph: LinkGraph,
+}
+
+impl Workspace {
+    // Load every page file under `root`, skipping hidden directories. Symbolic links to
+    // directories are not followed, so that a link to a parent cannot loop, and subdirectories
+    // that cannot be read are skipped
+    pub fn load(&mut self, root: &Path) -> io::Result<()> {
+        for entry in fs::read_dir(root)?.flatten() {
+            let path = entry.path();
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if hidden {
+                continue;
+            }
+
+            // The type of the entry itself, not of what a link points to
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                let _ = self.load(&path);
+            } else if is_page_file(&path)
+                && let Some(uri) = path_to_uri(&path)
+                && let Ok(text) = fs::read_to_string(&path)
+            {
+                self.update(uri, text);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn update(&mut self, uri: Uri, text: String) {
+        self.remove(&uri);
+
+        let mut input_stream = text.as_str();
+        let Ok(page) = crate::parse_page(&mut input_stream, &()) else {
+            return;
+        };
+        self.graph.add_page(&page.title, &page.body);
+        let file = PageFile {
+            title: page.title,
+            tokens: crate::tokenize(&text, &()),
+            text,
+        };
+        self.pages.insert(uri, file);
+    }
+
+    pub fn remove(&mut self, uri: &Uri) {
+        if let Some(file) = self.pages.remove(uri) {
+            self.graph.remove_page(&file.title);
+        }
+    }
+
+    // Reload a page from disk, e.g., when the editor closes it without saving
+    pub fn reload(&mut self, uri: &Uri) {
+        match uri_to_path(uri).and_then(|path| fs::read_to_string(path).ok()) {
+            Some(text) => self.update(uri.clone(), text),
+            None => self.remove(uri),
+        }
+    }
+
+    pub fn get(&self, uri: &Uri) -> Option<&PageFile> {
+        self.pages.get(uri)
+    }
+
+    pub fn find(&self, title: &str) -> Option<(&Uri, &PageFile)> {
+        let key = normalize_title(title);
+        self.pages
+            .iter()
+            .find(|(_, file)| normalize_title(&file.title) == key)
+    }
+
+    pub fn titles(&self) -> impl Iterator<Item = &str> {
+        self.pages.values().map(|file| file.title.as_str())
+    }
+
+    pub fn backlinks(&self, title: &str) -> impl Iterator<Item = (&Uri, &PageFile)> {
+        self.graph
+            .backlinks(title)
+            .into_iter()
+            .filter_map(|source| self.find(source))
+    }
+}
+
+fn is_page_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension))
+}
+
+fn path_to_uri(path: &Path) -> Option<Uri> {
+    let path = path.canonicalize().ok()?;
+    url::Url::from_file_path(path).ok()?.as_str().parse().ok()
+}
+
+pub(crate) fn uri_to_path(uri: &Uri) -> Option<std::path::PathBuf> {
+    url::Url::parse(uri.as_str()).ok()?.to_file_path().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_skips_directory_links() {
+        let root = std::env::temp_dir().join(format!("cosy-workspace-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.cosense"), "A\n[B]").unwrap();
+        fs::write(root.join("sub/b.txt"), "B").unwrap();
+        fs::write(root.join("notes.md"), "C").unwrap();
+        // A link back to the root would recurse forever if followed
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+
+        let mut workspace = Workspace::default();
+        let loaded = workspace.load(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        loaded.unwrap();
+        let mut titles: Vec<_> = workspace.titles().collect();
+        titles.sort();
+        assert_eq!(titles, ["A", "B"]);
+        assert_eq!(workspace.backlinks("B").count(), 1);
+    }
+}
@@ -0,0 +1,399 @@
+//! Markdown export of parsed documents.
+//!
+//! Top-level lines become paragraphs and indented blocks become (ordered) list items. Custom
+//! nodes produced by an extension are exported through the [`RenderMarkdown`] trait.
+
+use crate::Either;
+use crate::ast::{
+    Block, BlockContent, CodeBlockMeta, Document, Grouped, Link, Node, TableCell, group_quotes,
+};
+use crate::links::page_path;
+use crate::url::{UrlKind, openstreetmap_url};
+use std::fmt::Write;
+
+/// Renders a custom extension node to Markdown.
+pub trait RenderMarkdown {
+    /// Appends the Markdown representation of `self` to `out`.
+    fn render_markdown(&self, out: &mut String);
+}
+
+impl RenderMarkdown for () {
+    fn render_markdown(&self, _out: &mut String) {}
+}
+
+impl<L: RenderMarkdown, R: RenderMarkdown> RenderMarkdown for Either<L, R> {
+    fn render_markdown(&self, out: &mut String) {
+        match self {
+            Either::Left(left) => left.render_markdown(out),
+            Either::Right(right) => right.render_markdown(out),
+        }
+    }
+}
+
+/// Renders a whole document to Markdown.
+pub fn render<T: RenderMarkdown>(document: &Document<T>) -> String {
+    let mut out = String::new();
+    // The open list items, as their indent and the column their content starts at
+    let mut items: Vec<(usize, usize)> = Vec::new();
+
+    for item in group_quotes(document) {
+        let (body, number) = match &item {
+            // Empty lines only separate paragraphs
+            Grouped::Block(Block {
+                indent: 0,
+                content: BlockContent::Line(nodes),
+            }) if nodes.is_empty() => continue,
+            Grouped::Block(block) => {
+                let number = match block.content {
+                    BlockContent::NumberedLine { number, .. } => Some(number),
+                    _ => None,
+                };
+                (render_block(block), number)
+            }
+            Grouped::Quote(group) => (render_quote(&group.lines), None),
+        };
+
+        let indent = item.indent();
+        if indent == 0 {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&body);
+            out.push('\n');
+            items.clear();
+            continue;
+        }
+
+        if items.is_empty() && !out.is_empty() {
+            out.push('\n');
+        }
+
+        // A nested item starts at the content of its parent, whatever the width of its marker
+        while items.last().is_some_and(|&(parent, _)| parent >= indent) {
+            items.pop();
+        }
+        let column = items.last().map_or(0, |&(_, column)| column);
+        let padding = " ".repeat(column);
+        let marker = match number {
+            Some(number) => format!("{number}. "),
+            None => "- ".to_string(),
+        };
+        items.push((indent, column + marker.len()));
+        let continuation = format!("{padding}{}", " ".repeat(marker.len()));
+        for (i, line) in body.lines().enumerate() {
+            if i == 0 {
+                let _ = writeln!(out, "{padding}{marker}{line}");
+            } else if line.is_empty() {
+                out.push('\n');
+            } else {
+                let _ = writeln!(out, "{continuation}{line}");
+            }
+        }
+        if body.is_empty() {
+            let _ = writeln!(out, "{padding}{}", marker.trim_end());
+        }
+    }
+
+    out
+}
+
+/// Renders a sequence of inline nodes to Markdown.
+pub fn render_nodes<T: RenderMarkdown>(nodes: &[Node<T>]) -> String {
+    let mut out = String::new();
+    push_nodes(nodes, &mut out);
+    out
+}
+
+fn render_block<T: RenderMarkdown>(block: &Block<T>) -> String {
+    let mut out = String::new();
+    match &block.content {
+        BlockContent::Line(nodes) | BlockContent::NumberedLine { nodes, .. } => {
+            push_nodes(nodes, &mut out)
+        }
+        BlockContent::CodeBlock { meta, content, .. } => {
+            let fence = if content.contains("```") {
+                "````"
+            } else {
+                "```"
+            };
+            let _ = writeln!(out, "{fence}{}", code_language(meta));
+            if !content.is_empty() {
+                let _ = writeln!(out, "{content}");
+            }
+            out.push_str(fence);
+        }
+        BlockContent::Table {
+            columns,
+            header,
+            rows,
+            ..
+        } => {
+            if *columns == 0 {
+                return out;
+            }
+            push_table_row(header, &mut out);
+            out.push_str("\n|");
+            out.push_str(&" --- |".repeat(*columns));
+            for row in rows {
+                out.push('\n');
+                push_table_row(row, &mut out);
+            }
+        }
+        BlockContent::Quote(nodes) => out.push_str(&render_quote(&[nodes])),
+        BlockContent::Custom(custom) => custom.render_markdown(&mut out),
+    }
+    out
+}
+
+fn push_table_row<T: RenderMarkdown>(cells: &[TableCell<T>], out: &mut String) {
+    out.push('|');
+    for cell in cells {
+        out.push(' ');
+        out.push_str(&render_nodes(&cell.nodes).replace('|', "\\|"));
+        out.push_str(" |");
+    }
+}
+
+// Quote lines of a paragraph are joined with hard line breaks
+fn render_quote<T: RenderMarkdown>(lines: &[&[Node<T>]]) -> String {
+    lines
+        .iter()
+        .map(|nodes| format!("> {}", render_nodes(nodes)))
+        .collect::<Vec<_>>()
+        .join("\\\n")
+}
+
+// The info string of a fenced code block is the resolved language
+fn code_language(meta: &CodeBlockMeta) -> &'static str {
+    meta.resolve()
+        .language
+        .map_or("", |language| language.as_str())
+}
+
+fn push_nodes<T: RenderMarkdown>(nodes: &[Node<T>], out: &mut String) {
+    for node in nodes {
+        push_node(node, out);
+    }
+}
+
+fn push_node<T: RenderMarkdown>(node: &Node<T>, out: &mut String) {
+    match node {
+        Node::Text(text) => push_escaped(text, out),
+        Node::Link(link) => push_link(link, out),
+        Node::Image { src, href } => match href {
+            Some(href) => {
+                out.push_str("[![](");
+                push_destination(src, out);
+                out.push_str(")](");
+                push_destination(href, out);
+                out.push(')');
+            }
+            None => {
+                out.push_str("![](");
+                push_destination(src, out);
+                out.push(')');
+            }
+        },
+        Node::Embed { url, kind } => match kind {
+            UrlKind::Image => {
+                out.push_str("![](");
+                push_destination(url, out);
+                out.push(')');
+            }
+            _ => push_autolink(url, out),
+        },
+        Node::Icon { name, count } => {
+            for _ in 0..*count {
+                out.push_str("![");
+                push_escaped(name, out);
+                let _ = write!(out, "](./{}/icon)", page_path(name));
+            }
+        }
+        Node::Location {
+            lat,
+            lon,
+            zoom,
+            label,
+        } => {
+            out.push('[');
+            match label {
+                Some(label) => push_escaped(label, out),
+                None => {
+                    let _ = write!(out, "{lat},{lon}");
+                }
+            }
+            out.push_str("](");
+            push_destination(&openstreetmap_url(*lat, *lon, *zoom), out);
+            out.push(')');
+        }
+        Node::InlineCode(code) => {
+            let fence = if code.contains('`') { "`` " } else { "`" };
+            let _ = write!(
+                out,
+                "{fence}{code}{}",
+                fence.chars().rev().collect::<String>()
+            );
+        }
+        Node::Math(tex) => {
+            let _ = write!(out, "${tex}$");
+        }
+        Node::Decoration { decos, nodes } => {
+            let mut marks = String::new();
+            if decos.contains('*') {
+                marks.push_str("**");
+            }
+            if decos.contains('/') {
+                marks.push('*');
+            }
+            if decos.contains('-') {
+                marks.push_str("~~");
+            }
+            let inner = render_nodes(nodes);
+            // Emphasis markers must hug non-whitespace content
+            if marks.is_empty() || inner.trim().is_empty() {
+                out.push_str(&inner);
+            } else {
+                let closing: String = marks.chars().rev().collect();
+                let _ = write!(out, "{marks}{inner}{closing}");
+            }
+        }
+        Node::Custom(custom) => custom.render_markdown(out),
+    }
+}
+
+fn push_link<T: RenderMarkdown>(link: &Link<T>, out: &mut String) {
+    match link {
+        Link::Page(name) => {
+            out.push('[');
+            push_escaped(name, out);
+            let _ = write!(out, "](./{})", page_path(name));
+        }
+        Link::Anchor { page, anchor } => {
+            out.push('[');
+            push_escaped(page, out);
+            out.push('#');
+            push_escaped(anchor, out);
+            let _ = write!(out, "](./{}#", page_path(page));
+            push_destination(anchor, out);
+            out.push(')');
+        }
+        Link::Url(url) => push_autolink(url, out),
+        Link::WithLabel { href, label } => {
+            out.push('[');
+            push_nodes(label, out);
+            out.push_str("](");
+            push_destination(href, out);
+            out.push(')');
+        }
+    }
+}
+
+fn push_autolink(url: &str, out: &mut String) {
+    out.push('<');
+    push_destination(url, out);
+    out.push('>');
+}
+
+// Percent-encode the characters that would end a link destination or an autolink early, or
+// escape the character after them
+fn push_destination(url: &str, out: &mut String) {
+    for c in url.chars() {
+        if c.is_ascii_control() || matches!(c, ' ' | '(' | ')' | '<' | '>' | '\\') {
+            let _ = write!(out, "%{:02X}", c as u32);
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+fn push_escaped(s: &str, out: &mut String) {
+    for c in s.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '$') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_str(input: &str) -> String {
+        let mut input_stream = input;
+        let document = crate::parse(&mut input_stream, &()).unwrap();
+        render(&document)
+    }
+
+    #[test]
+    fn render_inline_nodes() {
+        assert_eq!(
+            render_str("[* bold] [Page Name] [https://example.com label] `a*b`"),
+            "**bold** [Page Name](./Page_Name) [label](https://example.com) `a*b`\n"
+        );
+    }
+
+    #[test]
+    fn render_lists() {
+        assert_eq!(
+            render_str("Steps\n 1. first\n  detail\n 2. second\n\nDone"),
+            "Steps\n\n1. first\n   - detail\n2. second\n\nDone\n"
+        );
+    }
+
+    #[test]
+    fn render_lists_under_wide_numbers() {
+        assert_eq!(
+            render_str("List\n 9. nine\n  a\n 10. ten\n  b\n   c"),
+            "List\n\n9. nine\n   - a\n10. ten\n    - b\n      - c\n"
+        );
+    }
+
+    #[test]
+    fn render_unsafe_destinations() {
+        assert_eq!(
+            render_str("[https://example.com/a_(b)) label]"),
+            "[label](https://example.com/a_%28b%29%29)\n"
+        );
+        assert_eq!(
+            render_nodes::<()>(&[
+                Node::Image {
+                    src: "https://example.com/a b.png".into(),
+                    href: Some("https://example.com/)".into()),
+                },
+                Node::Link(Link::Url("https://example.com/a b>)".into())),
+                Node::Link(Link::Anchor {
+                    page: "P".into(),
+                    anchor: "x)\ny".into(),
+                }),
+            ]),
+            "[![](https://example.com/a%20b.png)](https://example.com/%29)\
+             <https://example.com/a%20b%3E%29>\
+             [P#x)\ny](./P#x%29%0Ay)"
+        );
+    }
+
+    #[test]
+    fn render_quote_groups() {
+        assert_eq!(
+            render_str("> a\n> b\n\nc\n > d\n > e"),
+            "> a\\\n> b\n\nc\n\n- > d\\\n  > e\n"
+        );
+    }
+
+    #[test]
+    fn render_code_block_in_list() {
+        assert_eq!(
+            render_str(" code:main.rs\n  fn main() {}\n  \n  // end"),
+            "- ```rust\n  fn main() {}\n\n  // end\n  ```\n"
+        );
+    }
+
+    #[test]
+    fn render_table() {
+        assert_eq!(
+            render_str("table:t\n a\tb\n 1\t2|3"),
+            "| a | b |\n| --- | --- |\n| 1 | 2\\|3 |\n"
+        );
+    }
+}
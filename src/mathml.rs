@@ -0,0 +1,729 @@
+//! Conversion of TeX math to MathML (requires the `mathml` feature).
+//!
+//! Only the subset of TeX commonly written in `[$ ]` is supported: fractions, roots, sub- and
+//! superscripts, Greek letters, big operators (`\sum`, `\int`, ...), common symbols,
+//! `\left`/`\right` fences, text and matrix environments. [`render`] falls back to the raw TeX
+//! when an expression uses anything else, so output never loses information.
+
+use crate::html::escape;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// An error raised when TeX cannot be converted.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MathError {
+    /// The expression uses a command or environment outside the supported subset.
+    Unsupported(String),
+    /// Braces, `\left`/`\right` or environments are not balanced.
+    Unbalanced,
+    /// A command (e.g., `\frac`) or script (`_` or `^`) is missing its argument.
+    MissingArgument(String),
+    /// Groups, commands or environments are nested deeper than [`MAX_DEPTH`].
+    TooDeep,
+}
+
+/// The deepest nesting of groups, command arguments and environments that is converted.
+pub const MAX_DEPTH: usize = 100;
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MathError::Unsupported(name) => write!(f, "unsupported TeX construct: {name}"),
+            MathError::Unbalanced => write!(f, "unbalanced braces or environments"),
+            MathError::MissingArgument(name) => write!(f, "missing argument for {name}"),
+            MathError::TooDeep => write!(f, "nested deeper than {MAX_DEPTH} levels"),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
+/// Converts TeX to a `<math>` element.
+///
+/// Set `display` for block-level math. The TeX source is kept as an annotation.
+pub fn to_mathml(tex: &str, display: bool) -> Result<String, MathError> {
+    let tokens = tokenize(tex);
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        depth: 0,
+    };
+    let body = parser.parse_row(&[])?;
+    if parser.pos < tokens.len() {
+        return Err(MathError::Unbalanced);
+    }
+
+    let display = if display { " display=\"block\"" } else { "" };
+    Ok(format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"{display}><semantics>{body}\
+         <annotation encoding=\"application/x-tex\">{}</annotation></semantics></math>",
+        escape(tex)
+    ))
+}
+
+/// Converts TeX to MathML, or to the escaped raw TeX in a `<span class="math">` if the
+/// expression is not supported.
+pub fn render(tex: &str) -> String {
+    to_mathml(tex, false).unwrap_or_else(|_| format!("<span class=\"math\">{}</span>", escape(tex)))
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Command(String),
+    Open,
+    Close,
+    Sup,
+    Sub,
+    Align,
+    NewRow,
+    Number(String),
+    Letter(char),
+    Symbol(char),
+    Text(String),
+}
+
+const TEXT_COMMANDS: &[&str] = &[
+    "text",
+    "mathrm",
+    "mathbf",
+    "mathit",
+    "mathbb",
+    "mathcal",
+    "operatorname",
+];
+
+fn tokenize(tex: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<CharIndices> = tex.char_indices().peekable();
+
+    while let Some((_, c)) = chars.next() {
+        let token = match c {
+            '\\' => match chars.next() {
+                Some((_, '\\')) => Token::NewRow,
+                Some((_, c)) if c.is_ascii_alphabetic() => {
+                    let mut name = c.to_string();
+                    while let Some(&(_, c)) = chars.peek() {
+                        if !c.is_ascii_alphabetic() {
+                            break;
+                        }
+                        name.push(c);
+                        chars.next();
+                    }
+                    // The argument of text commands and environments is kept verbatim, spaces
+                    // included
+                    let verbatim =
+                        TEXT_COMMANDS.contains(&name.as_str()) || name == "begin" || name == "end";
+                    if verbatim && chars.peek().is_some_and(|&(_, c)| c == '{') {
+                        chars.next();
+                        let mut text = String::new();
+                        let mut depth = 0;
+                        for (_, c) in chars.by_ref() {
+                            match c {
+                                '{' => depth += 1,
+                                '}' if depth == 0 => break,
+                                '}' => depth -= 1,
+                                _ => {}
+                            }
+                            text.push(c);
+                        }
+                        tokens.push(Token::Command(name));
+                        Token::Text(text)
+                    } else {
+                        Token::Command(name)
+                    }
+                }
+                Some((_, c)) => Token::Command(c.to_string()),
+                None => Token::Symbol('\\'),
+            },
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '^' => Token::Sup,
+            '_' => Token::Sub,
+            '&' => Token::Align,
+            c if c.is_whitespace() => continue,
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    number.push(c);
+                    chars.next();
+                }
+                Token::Number(number)
+            }
+            c if c.is_alphabetic() => Token::Letter(c),
+            c => Token::Symbol(c),
+        };
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    // The number of atoms being parsed, which bounds the recursion
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    // Parses terms until a closing token; `stops` lists commands that end the row
+    fn parse_row(&mut self, stops: &[&str]) -> Result<String, MathError> {
+        let mut items = Vec::new();
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Close | Token::Align | Token::NewRow => break,
+                Token::Command(name) if stops.contains(&name.as_str()) => break,
+                _ => items.push(self.parse_term()?),
+            }
+        }
+        Ok(match items.len() {
+            1 => items.remove(0),
+            _ => format!("<mrow>{}</mrow>", items.concat()),
+        })
+    }
+
+    fn parse_term(&mut self) -> Result<String, MathError> {
+        let (base, limits) = self.parse_atom()?;
+
+        let mut sub = None;
+        let mut sup = None;
+        loop {
+            match self.peek() {
+                Some(Token::Sub) if sub.is_none() => {
+                    self.pos += 1;
+                    sub = Some(self.parse_argument("_")?);
+                }
+                Some(Token::Sup) if sup.is_none() => {
+                    self.pos += 1;
+                    sup = Some(self.parse_argument("^")?);
+                }
+                _ => break,
+            }
+        }
+
+        let (under, over, both) = if limits {
+            ("munder", "mover", "munderover")
+        } else {
+            ("msub", "msup", "msubsup")
+        };
+        Ok(match (sub, sup) {
+            (None, None) => base,
+            (Some(sub), None) => format!("<{under}>{base}{sub}</{under}>"),
+            (None, Some(sup)) => format!("<{over}>{base}{sup}</{over}>"),
+            (Some(sub), Some(sup)) => format!("<{both}>{base}{sub}{sup}</{both}>"),
+        })
+    }
+
+    // A braced group or a single atom, as taken by commands and scripts
+    fn parse_argument(&mut self, command: &str) -> Result<String, MathError> {
+        match self.peek() {
+            None | Some(Token::Close | Token::Sub | Token::Sup | Token::Align | Token::NewRow) => {
+                Err(MathError::MissingArgument(command.to_string()))
+            }
+            _ => Ok(self.parse_atom()?.0),
+        }
+    }
+
+    // Returns the MathML of the atom and whether its scripts are placed as limits. Every nested
+    // group, argument and environment goes through here, so this is where the depth is limited
+    fn parse_atom(&mut self) -> Result<(String, bool), MathError> {
+        if self.depth == MAX_DEPTH {
+            return Err(MathError::TooDeep);
+        }
+        self.depth += 1;
+        let atom = self.parse_nested_atom();
+        self.depth -= 1;
+        atom
+    }
+
+    fn parse_nested_atom(&mut self) -> Result<(String, bool), MathError> {
+        let token = self.next().ok_or(MathError::Unbalanced)?;
+        let mathml = match token {
+            Token::Open => {
+                let row = self.parse_row(&[])?;
+                self.expect_close()?;
+                row
+            }
+            Token::Number(n) => format!("<mn>{n}</mn>"),
+            Token::Letter(c) => format!("<mi>{}</mi>", escape(&c.to_string())),
+            Token::Symbol(c) => format!("<mo>{}</mo>", escape(&c.to_string())),
+            Token::Command(name) => return self.parse_command(&name),
+            Token::Text(text) => format!("<mtext>{}</mtext>", escape(&text)),
+            Token::Close | Token::Sub | Token::Sup | Token::Align | Token::NewRow => {
+                return Err(MathError::Unbalanced);
+            }
+        };
+        Ok((mathml, false))
+    }
+
+    fn parse_command(&mut self, name: &str) -> Result<(String, bool), MathError> {
+        if let Some(c) = greek(name) {
+            return Ok((format!("<mi>{c}</mi>"), false));
+        }
+        if let Some(c) = big_operator(name) {
+            let limits = !matches!(name, "int" | "iint" | "iiint" | "oint");
+            return Ok((format!("<mo>{c}</mo>"), limits));
+        }
+        if let Some(c) = symbol(name) {
+            return Ok((format!("<mo>{}</mo>", escape(c)), false));
+        }
+        if FUNCTIONS.contains(&name) {
+            let limits = matches!(name, "lim" | "max" | "min" | "sup" | "inf");
+            return Ok((format!("<mi mathvariant=\"normal\">{name}</mi>"), limits));
+        }
+
+        // Errors name the command as written
+        let command = format!("\\{name}");
+        let mathml = match name {
+            "frac" | "dfrac" | "tfrac" => {
+                let num = self.parse_argument(&command)?;
+                let den = self.parse_argument(&command)?;
+                format!("<mfrac>{num}{den}</mfrac>")
+            }
+            "sqrt" => {
+                if self.peek() == Some(&Token::Symbol('[')) {
+                    self.pos += 1;
+                    let index = self.parse_until_symbol(']')?;
+                    let radicand = self.parse_argument(&command)?;
+                    format!("<mroot>{radicand}{index}</mroot>")
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.parse_argument(&command)?)
+                }
+            }
+            name if TEXT_COMMANDS.contains(&name) => {
+                let text = self.raw_argument(&command)?;
+                match name {
+                    "text" => format!("<mtext>{}</mtext>", escape(&text)),
+                    "operatorname" | "mathrm" => {
+                        format!("<mi mathvariant=\"normal\">{}</mi>", escape(&text))
+                    }
+                    _ => {
+                        let variant = match name {
+                            "mathbf" => "bold",
+                            "mathit" => "italic",
+                            "mathbb" => "double-struck",
+                            _ => "script",
+                        };
+                        format!("<mi mathvariant=\"{variant}\">{}</mi>", escape(&text))
+                    }
+                }
+            }
+            "left" => {
+                let open = self.fence(&command)?;
+                let body = self.parse_row(&["right"])?;
+                if self.next() != Some(Token::Command("right".to_string())) {
+                    return Err(MathError::Unbalanced);
+                }
+                let close = self.fence("\\right")?;
+                format!("<mrow>{open}{body}{close}</mrow>")
+            }
+            "begin" => self.parse_environment()?,
+            "," | ":" | ";" | " " | "quad" | "qquad" | "!" => {
+                let width = match name {
+                    "," => "0.1667em",
+                    ":" => "0.2222em",
+                    ";" => "0.2778em",
+                    " " => "0.25em",
+                    "quad" => "1em",
+                    "qquad" => "2em",
+                    _ => "-0.1667em",
+                };
+                format!("<mspace width=\"{width}\"/>")
+            }
+            "{" | "}" | "|" | "%" | "$" | "#" | "&" | "_" => {
+                format!("<mo>{}</mo>", escape(name))
+            }
+            _ => return Err(MathError::Unsupported(format!("\\{name}"))),
+        };
+        Ok((mathml, false))
+    }
+
+    fn expect_close(&mut self) -> Result<(), MathError> {
+        match self.next() {
+            Some(Token::Close) => Ok(()),
+            _ => Err(MathError::Unbalanced),
+        }
+    }
+
+    fn parse_until_symbol(&mut self, end: char) -> Result<String, MathError> {
+        let mut items = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Token::Symbol(c)) if *c == end => {
+                    self.pos += 1;
+                    break;
+                }
+                None => return Err(MathError::Unbalanced),
+                _ => items.push(self.parse_term()?),
+            }
+        }
+        Ok(format!("<mrow>{}</mrow>", items.concat()))
+    }
+
+    // The literal text of a braced argument, as kept by the tokenizer
+    fn raw_argument(&mut self, command: &str) -> Result<String, MathError> {
+        match self.next() {
+            Some(Token::Text(text)) => Ok(text),
+            _ => Err(MathError::MissingArgument(command.to_string())),
+        }
+    }
+
+    // The delimiter after \left or \right; `.` is an invisible fence
+    fn fence(&mut self, command: &str) -> Result<String, MathError> {
+        let fence = match self.next() {
+            Some(Token::Symbol('.')) | Some(Token::Number(_)) => String::new(),
+            Some(Token::Symbol(c)) => c.to_string(),
+            Some(Token::Command(name)) => match name.as_str() {
+                "{" | "}" | "|" => name,
+                "langle" => "\u{27e8}".to_string(),
+                "rangle" => "\u{27e9}".to_string(),
+                "lfloor" => "\u{230a}".to_string(),
+                "rfloor" => "\u{230b}".to_string(),
+                "lceil" => "\u{2308}".to_string(),
+                "rceil" => "\u{2309}".to_string(),
+                _ => return Err(MathError::Unsupported(format!("{command}\\{name}"))),
+            },
+            _ => return Err(MathError::MissingArgument(command.to_string())),
+        };
+        Ok(format!(
+            "<mo fence=\"true\" stretchy=\"true\">{}</mo>",
+            escape(&fence)
+        ))
+    }
+
+    fn parse_environment(&mut self) -> Result<String, MathError> {
+        let name = self.raw_argument("\\begin")?;
+        let (open, close) = match name.as_str() {
+            "matrix" | "array" | "aligned" | "align" => ("", ""),
+            "pmatrix" => ("(", ")"),
+            "bmatrix" => ("[", "]"),
+            "Bmatrix" => ("{", "}"),
+            "vmatrix" => ("|", "|"),
+            "Vmatrix" => ("\u{2016}", "\u{2016}"),
+            "cases" => ("{", ""),
+            _ => return Err(MathError::Unsupported(format!("\\begin{{{name}}}"))),
+        };
+        // Column specifications of array are ignored
+        if name == "array" && self.peek() == Some(&Token::Open) {
+            self.parse_argument("\\begin{array}")?;
+        }
+
+        let mut rows = Vec::new();
+        let mut cells = Vec::new();
+        loop {
+            cells.push(format!("<mtd>{}</mtd>", self.parse_row(&["end"])?));
+            match self.next() {
+                Some(Token::Align) => {}
+                Some(Token::NewRow) => {
+                    rows.push(format!("<mtr>{}</mtr>", cells.concat()));
+                    cells.clear();
+                }
+                Some(Token::Command(c)) if c == "end" => {
+                    if self.raw_argument("\\end")? != name {
+                        return Err(MathError::Unbalanced);
+                    }
+                    break;
+                }
+                _ => return Err(MathError::Unbalanced),
+            }
+        }
+        // A trailing \\ leaves an empty last row
+        if !(cells.len() == 1 && cells[0] == "<mtd><mrow></mrow></mtd>") {
+            rows.push(format!("<mtr>{}</mtr>", cells.concat()));
+        }
+
+        let align = if name == "cases" {
+            " columnalign=\"left\""
+        } else {
+            ""
+        };
+        let table = format!("<mtable{align}>{}</mtable>", rows.concat());
+        let fence = |c: &str| {
+            if c.is_empty() {
+                String::new()
+            } else {
+                format!("<mo fence=\"true\">{}</mo>", escape(c))
+            }
+        };
+        Ok(format!(
+            "<mrow>{}{table}{}</mrow>",
+            fence(open),
+            fence(close)
+        ))
+    }
+}
+
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "log", "ln", "lg", "exp", "lim", "max", "min", "sup", "inf", "det", "gcd", "deg", "dim", "ker",
+    "arg", "Pr",
+];
+
+fn greek(name: &str) -> Option<char> {
+    Some(match name {
+        "alpha" => 'α',
+        "beta" => 'β',
+        "gamma" => 'γ',
+        "delta" => 'δ',
+        "epsilon" => 'ϵ',
+        "varepsilon" => 'ε',
+        "zeta" => 'ζ',
+        "eta" => 'η',
+        "theta" => 'θ',
+        "vartheta" => 'ϑ',
+        "iota" => 'ι',
+        "kappa" => 'κ',
+        "lambda" => 'λ',
+        "mu" => 'μ',
+        "nu" => 'ν',
+        "xi" => 'ξ',
+        "pi" => 'π',
+        "varpi" => 'ϖ',
+        "rho" => 'ρ',
+        "varrho" => 'ϱ',
+        "sigma" => 'σ',
+        "varsigma" => 'ς',
+        "tau" => 'τ',
+        "upsilon" => 'υ',
+        "phi" => 'ϕ',
+        "varphi" => 'φ',
+        "chi" => 'χ',
+        "psi" => 'ψ',
+        "omega" => 'ω',
+        "Gamma" => 'Γ',
+        "Delta" => 'Δ',
+        "Theta" => 'Θ',
+        "Lambda" => 'Λ',
+        "Xi" => 'Ξ',
+        "Pi" => 'Π',
+        "Sigma" => 'Σ',
+        "Upsilon" => 'Υ',
+        "Phi" => 'Φ',
+        "Psi" => 'Ψ',
+        "Omega" => 'Ω',
+        _ => return None,
+    })
+}
+
+fn big_operator(name: &str) -> Option<char> {
+    Some(match name {
+        "sum" => '∑',
+        "prod" => '∏',
+        "coprod" => '∐',
+        "int" => '∫',
+        "iint" => '∬',
+        "iiint" => '∭',
+        "oint" => '∮',
+        "bigcup" => '⋃',
+        "bigcap" => '⋂',
+        _ => return None,
+    })
+}
+
+fn symbol(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "pm" => "±",
+        "mp" => "∓",
+        "times" => "×",
+        "div" => "÷",
+        "cdot" => "⋅",
+        "ast" => "∗",
+        "circ" => "∘",
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "neq" | "ne" => "≠",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "simeq" => "≃",
+        "propto" => "∝",
+        "ll" => "≪",
+        "gg" => "≫",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" | "implies" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" | "iff" => "⇔",
+        "mapsto" => "↦",
+        "infty" => "∞",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "forall" => "∀",
+        "exists" => "∃",
+        "neg" | "lnot" => "¬",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "cup" => "∪",
+        "cap" => "∩",
+        "emptyset" | "varnothing" => "∅",
+        "land" | "wedge" => "∧",
+        "lor" | "vee" => "∨",
+        "oplus" => "⊕",
+        "otimes" => "⊗",
+        "perp" => "⊥",
+        "parallel" => "∥",
+        "angle" => "∠",
+        "cdots" => "⋯",
+        "ldots" | "dots" => "…",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "prime" => "′",
+        "hbar" => "ℏ",
+        "ell" => "ℓ",
+        "Re" => "ℜ",
+        "Im" => "ℑ",
+        "aleph" => "ℵ",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "mid" => "∣",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The MathML without the <math> wrapper and the TeX annotation
+    fn body(tex: &str) -> String {
+        let mathml = to_mathml(tex, false).unwrap();
+        let start = mathml.find("<semantics>").unwrap() + "<semantics>".len();
+        let end = mathml.find("<annotation").unwrap();
+        mathml[start..end].to_string()
+    }
+
+    #[test]
+    fn convert_fraction_and_scripts() {
+        assert_eq!(
+            body("\\frac{a}{b^2}"),
+            "<mfrac><mi>a</mi><msup><mi>b</mi><mn>2</mn></msup></mfrac>"
+        );
+        assert_eq!(
+            body("x_i^{n+1}"),
+            "<msubsup><mi>x</mi><mi>i</mi><mrow><mi>n</mi><mo>+</mo><mn>1</mn></mrow></msubsup>"
+        );
+    }
+
+    #[test]
+    fn convert_big_operators() {
+        assert_eq!(
+            body("\\sum_{k=1}^n \\alpha_k"),
+            "<mrow><munderover><mo>∑</mo><mrow><mi>k</mi><mo>=</mo><mn>1</mn></mrow>\
+             <mi>n</mi></munderover><msub><mi>α</mi><mi>k</mi></msub></mrow>"
+        );
+        assert_eq!(
+            body("\\int_0^1"),
+            "<msubsup><mo>∫</mo><mn>0</mn><mn>1</mn></msubsup>"
+        );
+    }
+
+    #[test]
+    fn convert_matrix() {
+        assert_eq!(
+            body("\\begin{pmatrix} a & b \\\\ c & d \\end{pmatrix}"),
+            "<mrow><mo fence=\"true\">(</mo><mtable>\
+             <mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr>\
+             <mtr><mtd><mi>c</mi></mtd><mtd><mi>d</mi></mtd></mtr>\
+             </mtable><mo fence=\"true\">)</mo></mrow>"
+        );
+    }
+
+    #[test]
+    fn convert_roots_and_cases() {
+        assert_eq!(
+            body("\\sqrt[3]{x}"),
+            "<mroot><mi>x</mi><mrow><mn>3</mn></mrow></mroot>"
+        );
+        assert_eq!(
+            body("\\begin{cases} 1 & x > 0 \\\\ 0 & \\text{otherwise} \\\\ \\end{cases}"),
+            "<mrow><mo fence=\"true\">{</mo><mtable columnalign=\"left\">\
+             <mtr><mtd><mn>1</mn></mtd><mtd><mrow><mi>x</mi><mo>&gt;</mo><mn>0</mn></mrow></mtd></mtr>\
+             <mtr><mtd><mn>0</mn></mtd><mtd><mtext>otherwise</mtext></mtd></mtr>\
+             </mtable></mrow>"
+        );
+    }
+
+    #[test]
+    fn convert_fences_and_text() {
+        assert_eq!(
+            body("\\left( x \\right) \\text{if } x < 1"),
+            "<mrow><mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mi>x</mi>\
+             <mo fence=\"true\" stretchy=\"true\">)</mo></mrow><mtext>if </mtext>\
+             <mi>x</mi><mo>&lt;</mo><mn>1</mn></mrow>"
+        );
+    }
+
+    #[test]
+    fn annotation_and_display() {
+        let mathml = to_mathml("a<b", true).unwrap();
+        assert!(
+            mathml.starts_with(
+                "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\">"
+            )
+        );
+        assert!(mathml.ends_with(
+            "<annotation encoding=\"application/x-tex\">a&lt;b</annotation></semantics></math>"
+        ));
+    }
+
+    #[test]
+    fn fallback_to_raw_tex() {
+        assert_eq!(
+            to_mathml("\\unknown{x}", false),
+            Err(MathError::Unsupported("\\unknown".to_string()))
+        );
+        assert_eq!(to_mathml("{x", false), Err(MathError::Unbalanced));
+        assert_eq!(
+            to_mathml("\\frac{a}", false),
+            Err(MathError::MissingArgument("\\frac".to_string()))
+        );
+        assert_eq!(
+            to_mathml("\\left", false),
+            Err(MathError::MissingArgument("\\left".to_string()))
+        );
+        assert_eq!(
+            to_mathml("x^", false),
+            Err(MathError::MissingArgument("^".to_string()))
+        );
+        let nested = format!("{}x{}", "{".repeat(100_000), "}".repeat(100_000));
+        assert_eq!(to_mathml(&nested, false), Err(MathError::TooDeep));
+        assert_eq!(
+            to_mathml(&"\\sqrt".repeat(100_000), false),
+            Err(MathError::TooDeep)
+        );
+        let nested = format!(
+            "{}x{}",
+            "{".repeat(MAX_DEPTH - 1),
+            "}".repeat(MAX_DEPTH - 1)
+        );
+        assert!(to_mathml(&nested, false).is_ok());
+        assert_eq!(
+            render("\\unknown < x"),
+            "<span class=\"math\">\\unknown &lt; x</span>"
+        );
+    }
+}
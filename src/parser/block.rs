@@ -1,17 +1,20 @@
 use super::{code, line, quote, table};
-use crate::CosyParserExtension;
 use crate::ast::Block;
 use crate::tokens::{CODE_PREFIX, GT, TABLE_PREFIX};
+use crate::{CosyParserExtension, ExtensionContext};
 use winnow::Result as PResult;
 use winnow::combinator::{eof, not};
 use winnow::prelude::*;
 
-pub fn parse_block<'s, E>(input: &mut &'s str, extension: &'s E) -> PResult<Block<E::Output>>
+pub fn parse_block<'i, E>(
+    input: &mut &'i str,
+    ctx: ExtensionContext<'_, E>,
+) -> PResult<Block<'i, E::Output>>
 where
     E: CosyParserExtension,
 {
     // Ensure not EOF
-    let _ = not(eof).parse_next(input)?;
+    not(eof).parse_next(input)?;
 
     // 1. Calculate and consume indent
     let indent_len = input.chars().take_while(|&c| c == ' ').count();
@@ -19,18 +22,20 @@ where
         let _ = winnow::token::take(indent_len).parse_next(input)?;
     }
 
+    let ctx = ctx.at(indent_len, ctx.line());
+
     // 2. Determine block type
     // We look at the immediate content
     if (*input).starts_with(CODE_PREFIX) {
-        return code::parse_code_block::<E>(input, indent_len);
+        return code::parse_code_block(input, ctx);
     }
     if (*input).starts_with(TABLE_PREFIX) {
-        return table::parse_table(input, extension, indent_len);
+        return table::parse_table(input, ctx);
     }
     if (*input).starts_with(GT) {
-        return quote::parse_quote(input, extension, indent_len);
+        return quote::parse_quote(input, ctx);
     }
 
     // Default: Line
-    line::parse_line(input, extension, indent_len)
+    line::parse_line(input, ctx)
 }
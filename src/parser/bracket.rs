@@ -1,108 +1,372 @@
-use crate::CosyParserExtension;
 use crate::ast::Link;
 use crate::ast::Node;
+use crate::links::split_anchor;
 use crate::tokens::{DECO_CHARS, DOLLAR, ICON_SUFFIX, LBRACKET, RBRACKET};
-use crate::url::{UrlKind, infer_url_kind, is_url};
-use winnow::combinator::delimited;
+use crate::url::{Url, UrlClassifier, UrlKind, infer_url_kind_with, is_url};
+use crate::{BracketKind, CosyParserExtension, ExtensionContext, TokenKind};
+use winnow::Result as PResult;
 use winnow::error::ContextError;
 use winnow::prelude::*;
 use winnow::token::take_until;
 
+use super::location::parse_location;
 use super::node::parse_nodes;
 
-pub fn parse_bracket<'s, 'i, E>(
-    extension: &'s E,
-) -> impl Parser<&'i str, Node<E::Output>, ContextError> + 's
+pub fn parse_bracket<'c, 'i, E>(
+    ctx: ExtensionContext<'c, E>,
+) -> impl Parser<&'i str, Node<'i, E::Output>, ContextError> + 'c
 where
     E: CosyParserExtension,
 {
     move |input: &mut &'i str| {
-        let content: &str =
-            delimited(LBRACKET, take_until(0.., RBRACKET), RBRACKET).parse_next(input)?;
+        let (open, content, close) =
+            (LBRACKET.take(), bracket_content, RBRACKET.take()).parse_next(input)?;
+        ctx.token(open, TokenKind::Bracket);
+        ctx.token(close, TokenKind::Bracket);
+        let extension = ctx.extension();
+        // Extensions may parse text that is not part of the input, so they record nothing
+        let custom = |custom_node| {
+            ctx.token(content, TokenKind::Custom);
+            Node::Custom(custom_node)
+        };
 
-        if let Some(custom_node) = extension.parse_bracket(content) {
-            return Ok(Node::Custom(custom_node));
+        if let Some(custom_node) = extension.parse_bracket(content, &ctx.unrecorded()) {
+            return Ok(custom(custom_node));
         }
 
-        // Handle specific bracket types that need recursion or context
-
-        // 1. Decoration: [* bold], [*- bold strike]
-        // Condition: Starts with decoration chars followed by space
-        // We define decoration chars as sequence of *, -, /, _, !
-        // Simple check: take while matches decoration char
-        let mut chars_iter = content.chars();
-        let first_char = chars_iter.next();
-
-        if let Some(c) = first_char {
-            if c == DOLLAR {
-                // Math
-                let math_content = &content[1..];
-                return Ok(Node::Math(math_content.trim().to_string()));
-            } else if is_decoration_char(c) {
-                // Check if it's a decoration pattern: "decos "
-                // We need to find the first space
-                if let Some((decos, body)) = content.split_once(' ') {
-                    if decos.chars().all(is_decoration_char) {
-                        // It is a decoration
-                        let mut body_input = body;
-                        let nodes = parse_nodes(&mut body_input, extension)?;
-                        return Ok(Node::Decoration {
-                            decos: decos.to_string(),
-                            nodes,
-                        });
-                    }
+        // Extensions may replace a specific built-in form
+        let builtin = builtin_form(content, extension);
+        if let Some(custom_node) =
+            extension.before_builtin(builtin.kind(), content, &ctx.unrecorded())
+        {
+            return Ok(custom(custom_node));
+        }
+
+        Ok(match builtin {
+            Builtin::Math(tex) => {
+                ctx.token(&content[..content.len() - tex.len()], TokenKind::MathMark);
+                ctx.token(tex, TokenKind::Math);
+                Node::Math(tex.trim().into())
+            }
+            Builtin::Decoration { decos, mut body } => {
+                ctx.token(decos, TokenKind::Decoration);
+                record_separator(&ctx, content, decos, body);
+                Node::Decoration {
+                    decos: decos.into(),
+                    nodes: parse_nodes(&mut body, &ctx.with_text_kind(TokenKind::DecoratedText))?,
                 }
             }
-        }
+            Builtin::Icon(name) => {
+                ctx.token(content, TokenKind::Icon);
+                Node::Icon {
+                    name: name.into(),
+                    count: 1,
+                }
+            }
+            Builtin::Location(location) => {
+                ctx.token(content, TokenKind::Location);
+                location
+            }
+            Builtin::Image { src, href } => {
+                ctx.token(src, TokenKind::Url);
+                if let Some(href) = href {
+                    ctx.token(href, TokenKind::Url);
+                    record_separator(&ctx, content, src, href);
+                }
+                Node::Image {
+                    src: src.into(),
+                    href: href.map(Into::into),
+                }
+            }
+            Builtin::Embed(kind) => {
+                ctx.token(content, TokenKind::Url);
+                Node::Embed {
+                    url: content.into(),
+                    kind,
+                }
+            }
+            Builtin::Url => {
+                ctx.token(content, TokenKind::Url);
+                Node::Link(Link::Url(content.into()))
+            }
+            Builtin::LabeledUrl { href, mut label } => {
+                ctx.token(href, TokenKind::Url);
+                record_separator(&ctx, content, href, label);
+                Node::Link(Link::WithLabel {
+                    href: href.into(),
+                    label: parse_nodes(&mut label, &ctx.with_text_kind(TokenKind::LinkLabel))?,
+                })
+            }
+            // What would otherwise be a page link may be resolved by the extension (e.g., an alias)
+            Builtin::PageLink => match extension.fallback(content, &ctx.unrecorded()) {
+                Some(custom_node) => custom(custom_node),
+                None => {
+                    ctx.token(content, TokenKind::PageLink);
+                    Node::Link(page_link(content))
+                }
+            },
+        })
+    }
+}
+
+// Record the spaces between two parts of a bracket's content as a separator
+fn record_separator<E: ?Sized>(ctx: &ExtensionContext<'_, E>, content: &str, a: &str, b: &str) {
+    let offset = |part: &str| part.as_ptr() as usize - content.as_ptr() as usize;
+    let (first, second) = if offset(a) < offset(b) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let start = offset(first) + first.len();
+    ctx.token(&content[start..offset(second)], TokenKind::Separator);
+}
+
+// The built-in form of a bracket, decided before parsing nested nodes
+enum Builtin<'a, T> {
+    Math(&'a str),
+    Decoration { decos: &'a str, body: &'a str },
+    Icon(&'a str),
+    Location(Node<'a, T>),
+    Image { src: &'a str, href: Option<&'a str> },
+    Embed(UrlKind),
+    Url,
+    LabeledUrl { href: &'a str, label: &'a str },
+    PageLink,
+}
 
-        // 2. Icon: [name.icon] or [name.icon*3]
-        if content.ends_with(ICON_SUFFIX) {
-            // Simple icon
-            let name = content.trim_end_matches(ICON_SUFFIX);
-            return Ok(Node::Icon {
-                name: name.to_string(),
-                count: 1,
-            });
+impl<T> Builtin<'_, T> {
+    fn kind(&self) -> BracketKind {
+        match self {
+            Builtin::Math(_) => BracketKind::Math,
+            Builtin::Decoration { .. } => BracketKind::Decoration,
+            Builtin::Icon(_) => BracketKind::Icon,
+            Builtin::Location(_) => BracketKind::Location,
+            Builtin::Image { .. } => BracketKind::Image,
+            Builtin::Embed(_) => BracketKind::Embed,
+            Builtin::Url => BracketKind::Url,
+            Builtin::LabeledUrl { .. } => BracketKind::LabeledUrl,
+            Builtin::PageLink => BracketKind::PageLink,
         }
-        // TODO: Handle repetition [name.icon*3] if needed.
-
-        // 3. Links (recurse on label)
-        // Split by space
-        if let Some((left, right)) = content.split_once(' ') {
-            let left = left.trim();
-            let right = right.trim();
-
-            if is_url(left) {
-                // [url label]
-                let mut label_input = right;
-                let nodes = parse_nodes(&mut label_input, extension)?;
-                return Ok(Node::Link(Link::WithLabel {
-                    href: left.to_string(),
-                    label: nodes,
-                }));
-            } else if is_url(right) {
-                // [label url]
-                let mut label_input = left;
-                let nodes = parse_nodes(&mut label_input, extension)?;
-                return Ok(Node::Link(Link::WithLabel {
-                    href: right.to_string(),
-                    label: nodes,
-                }));
-            } else {
-                // [Page Name] - Space inside page name
-                return Ok(Node::Link(Link::Page(content.to_string())));
+    }
+}
+
+fn builtin_form<'a, E>(content: &'a str, extension: &E) -> Builtin<'a, E::Output>
+where
+    E: CosyParserExtension,
+{
+    let classify = |s: &str| infer_url_kind_with(s, &ExtensionClassifier(extension));
+
+    // 1. Math: [$ x^2], and decoration: [* bold], [*- bold strike]
+    // A decoration starts with decoration chars followed by a space
+    if let Some(tex) = content.strip_prefix(DOLLAR) {
+        return Builtin::Math(tex);
+    }
+    if content.starts_with(is_decoration_char)
+        && let Some((decos, body)) = content.split_once(' ')
+        && decos.chars().all(is_decoration_char)
+    {
+        return Builtin::Decoration { decos, body };
+    }
+
+    // 2. Icon: [name.icon]
+    // TODO: Handle repetition [name.icon*3] if needed.
+    if let Some(name) = content.strip_suffix(ICON_SUFFIX) {
+        return Builtin::Icon(name.trim_end_matches(ICON_SUFFIX));
+    }
+
+    // 3. Location: [N35.68,E139.76,Z14] or [N35.68,E139.76,Z14 place name]
+    if let Some(location) = parse_location(content) {
+        return Builtin::Location(location);
+    }
+
+    // 4. Links (recurse on label)
+    // Split by space
+    if let Some((left, right)) = content.split_once(' ') {
+        let left = left.trim();
+        let right = right.trim();
+
+        // [image-url link-url] or [link-url image-url]
+        match (classify(left), classify(right)) {
+            (Some(UrlKind::Image), Some(_)) => {
+                return Builtin::Image {
+                    src: left,
+                    href: Some(right),
+                };
             }
+            (Some(_), Some(UrlKind::Image)) => {
+                return Builtin::Image {
+                    src: right,
+                    href: Some(left),
+                };
+            }
+            _ => {}
         }
 
-        // 4. Simple content (Image, URL, Page)
-        match infer_url_kind(content) {
-            Some(UrlKind::Image) => Ok(Node::Image(content.to_string())),
-            Some(UrlKind::Other) => Ok(Node::Link(Link::Url(content.to_string()))),
-            None => Ok(Node::Link(Link::Page(content.to_string()))),
+        return if is_url(left) {
+            // [url label]
+            Builtin::LabeledUrl {
+                href: left,
+                label: right,
+            }
+        } else if is_url(right) {
+            // [label url]
+            Builtin::LabeledUrl {
+                href: right,
+                label: left,
+            }
+        } else {
+            // [Page Name] - Space inside page name
+            Builtin::PageLink
+        };
+    }
+
+    // 5. Simple content (Image, Embed, URL, Page)
+    match classify(content) {
+        Some(UrlKind::Image) => Builtin::Image {
+            src: content,
+            href: None,
+        },
+        Some(UrlKind::Other) => Builtin::Url,
+        Some(kind) => Builtin::Embed(kind),
+        None => Builtin::PageLink,
+    }
+}
+
+// The content up to the matching `]`, so that brackets can nest (e.g., `[* [link]]`).
+// Unbalanced content ends at the first `]`
+fn bracket_content<'i>(input: &mut &'i str) -> PResult<&'i str> {
+    let mut depth = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            LBRACKET => depth += 1,
+            RBRACKET if depth == 0 => {
+                let (content, rest) = input.split_at(i);
+                *input = rest;
+                return Ok(content);
+            }
+            RBRACKET => depth -= 1,
+            _ => {}
         }
     }
+    take_until(0.., RBRACKET).parse_next(input)
+}
+
+fn page_link<T>(content: &str) -> Link<'_, T> {
+    match split_anchor(content) {
+        (page, Some(anchor)) => Link::Anchor {
+            page: page.into(),
+            anchor: anchor.into(),
+        },
+        (page, None) => Link::Page(page.into()),
+    }
 }
 
 fn is_decoration_char(c: char) -> bool {
     DECO_CHARS.contains(c)
 }
+
+// Adapts the extension's URL hook to the classifier interface
+struct ExtensionClassifier<'a, E>(&'a E);
+
+impl<E: CosyParserExtension> UrlClassifier for ExtensionClassifier<'_, E> {
+    fn classify(&self, url: &Url) -> Option<UrlKind> {
+        self.0.classify_url(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_context;
+
+    fn parse(input: &str) -> Node<'_, ()> {
+        let mut input_stream = input;
+        parse_bracket(test_context(&(), 0))
+            .parse_next(&mut input_stream)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_image() {
+        assert_eq!(
+            parse("[https://gyazo.com/0123456789abcdef0123456789abcdef]"),
+            Node::Image {
+                src: "https://gyazo.com/0123456789abcdef0123456789abcdef".into(),
+                href: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_linked_image() {
+        let expected = Node::Image {
+            src: "https://example.com/image.png".into(),
+            href: Some("https://example.com".into()),
+        };
+        assert_eq!(
+            parse("[https://example.com/image.png https://example.com]"),
+            expected
+        );
+        assert_eq!(
+            parse("[https://example.com https://example.com/image.png]"),
+            expected
+        );
+    }
+
+    #[test]
+    fn parse_embed() {
+        assert_eq!(
+            parse("[https://youtu.be/dQw4w9WgXcQ?t=42]"),
+            Node::Embed {
+                url: "https://youtu.be/dQw4w9WgXcQ?t=42".into(),
+                kind: UrlKind::YouTube {
+                    id: "dQw4w9WgXcQ".into(),
+                    start: Some(42),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn parse_page_anchor() {
+        assert_eq!(
+            parse("[Some Page#5f3a1b2c3d4e5f6a7b8c9d0e]"),
+            Node::Link(Link::Anchor {
+                page: "Some Page".into(),
+                anchor: "5f3a1b2c3d4e5f6a7b8c9d0e".into(),
+            })
+        );
+        assert_eq!(parse("[C#]"), Node::Link(Link::Page("C#".into())));
+        assert_eq!(
+            parse("[C# tutorial]"),
+            Node::Link(Link::Page("C# tutorial".into()))
+        );
+    }
+
+    #[test]
+    fn parse_nested_brackets() {
+        assert_eq!(
+            parse("[* see [Page]]"),
+            Node::Decoration {
+                decos: "*".into(),
+                nodes: vec![
+                    Node::Text("see ".into()),
+                    Node::Link(Link::Page("Page".into())),
+                ],
+            }
+        );
+        // Unbalanced brackets end at the first `]`
+        assert_eq!(parse("[a[b]"), Node::Link(Link::Page("a[b".into())));
+    }
+
+    #[test]
+    fn parse_labeled_url_is_not_image() {
+        assert_eq!(
+            parse("[https://example.com Example]"),
+            Node::Link(Link::WithLabel {
+                href: "https://example.com".into(),
+                label: vec![Node::Text("Example".into())],
+            })
+        );
+    }
+}
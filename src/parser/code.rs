@@ -1,17 +1,24 @@
-use crate::CosyParserExtension;
 use crate::ast::{Block, BlockContent, CodeBlockMeta};
 use crate::tokens::CODE_PREFIX;
+use crate::{CosyParserExtension, ExtensionContext, TokenKind};
+use std::borrow::Cow;
 use winnow::Result as PResult;
 use winnow::prelude::*;
 use winnow::token::{any, take_till};
 
-pub fn parse_code_block<'s, E>(input: &mut &'s str, indent: usize) -> PResult<Block<E::Output>>
+pub fn parse_code_block<'i, E>(
+    input: &mut &'i str,
+    ctx: ExtensionContext<'_, E>,
+) -> PResult<Block<'i, E::Output>>
 where
     E: CosyParserExtension,
 {
+    let indent = ctx.indent();
     // "code:filename"
-    let _ = { CODE_PREFIX }.parse_next(input)?;
+    let prefix = { CODE_PREFIX }.parse_next(input)?;
+    ctx.token(prefix, TokenKind::BlockPrefix);
     let filename_line = take_till(0.., |c| c == '\n').parse_next(input)?;
+    ctx.token(filename_line, TokenKind::BlockName);
     let filename_line = if filename_line.trim().is_empty() {
         None
     } else {
@@ -50,7 +57,7 @@ where
 
     // Parse subsequent lines that are indented MORE than `indent`
     // We assume the block continues as long as lines are indented > indent.
-    let mut content = String::new();
+    let mut lines = Vec::new();
 
     loop {
         // Peek next line indent
@@ -67,9 +74,9 @@ where
 
         // Consume line
         let line = take_till(0.., |c| c == '\n').parse_next(input)?;
+        ctx.token(line, TokenKind::Code);
 
-        content.push_str(line);
-        content.push('\n');
+        lines.push(line);
 
         if !input.is_empty() && (*input).starts_with('\n') {
             let _ = any.parse_next(input)?;
@@ -79,10 +86,12 @@ where
         }
     }
 
-    // Remove last newline if added
-    if content.ends_with('\n') {
-        content.pop();
-    }
+    // Only multi-line content needs a copy without the indentation
+    let content = match lines[..] {
+        [] => Cow::Borrowed(""),
+        [line] => Cow::Borrowed(line),
+        _ => Cow::Owned(lines.join("\n")),
+    };
 
     Ok(Block {
         indent,
@@ -98,6 +107,7 @@ where
 mod tests {
     use super::*;
     use crate::ast::*;
+    use crate::parser::test_context;
 
     #[test]
     fn parse_code_block_with_filename() {
@@ -108,16 +118,16 @@ mod tests {
 "#;
 
         let mut input_stream = input;
-        let result = parse_code_block::<()>(&mut input_stream, 0);
+        let result = parse_code_block(&mut input_stream, test_context(&(), 0));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 0);
         assert_eq!(
             block.content,
             BlockContent::CodeBlock {
-                meta: CodeBlockMeta::Either("example.rs".to_string()),
+                meta: CodeBlockMeta::Either("example.rs".into()),
                 indent: 0,
-                content: "fn main() {\nprintln!(\"Hello, world!\");\n}".to_string(),
+                content: "fn main() {\nprintln!(\"Hello, world!\");\n}".into(),
             }
         );
     }
@@ -126,7 +136,7 @@ mod tests {
     fn parse_code_block_without_filename() {
         let input = "code:\n    print('Hello, World!')\n";
         let mut input_stream = input;
-        let result = parse_code_block::<()>(&mut input_stream, 0);
+        let result = parse_code_block(&mut input_stream, test_context(&(), 0));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 0);
@@ -135,7 +145,7 @@ mod tests {
             BlockContent::CodeBlock {
                 meta: CodeBlockMeta::None,
                 indent: 0,
-                content: "print('Hello, World!')".to_string(),
+                content: "print('Hello, World!')".into(),
             }
         );
     }
@@ -144,7 +154,7 @@ mod tests {
     fn parse_code_block_with_filename_and_filetype() {
         let input = "code:script.py(python)\n def greet():\n print('Hello')\n";
         let mut input_stream = input;
-        let result = parse_code_block::<()>(&mut input_stream, 0);
+        let result = parse_code_block(&mut input_stream, test_context(&(), 0));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 0);
@@ -152,11 +162,11 @@ mod tests {
             block.content,
             BlockContent::CodeBlock {
                 meta: CodeBlockMeta::Both {
-                    filename: "script.py".to_string(),
-                    filetype: "python".to_string(),
+                    filename: "script.py".into(),
+                    filetype: "python".into(),
                 },
                 indent: 0,
-                content: "def greet():\nprint('Hello')".to_string(),
+                content: "def greet():\nprint('Hello')".into(),
             }
         );
     }
@@ -165,16 +175,16 @@ mod tests {
     fn test_end_of_code_block() {
         let input = "code:example.py\n    print('Hello, World!')\nThis is outside the code block.";
         let mut input_stream = input;
-        let result = parse_code_block::<()>(&mut input_stream, 0);
+        let result = parse_code_block(&mut input_stream, test_context(&(), 0));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 0);
         assert_eq!(
             block.content,
             BlockContent::CodeBlock {
-                meta: CodeBlockMeta::Either("example.py".to_string()),
+                meta: CodeBlockMeta::Either("example.py".into()),
                 indent: 0,
-                content: "print('Hello, World!')".to_string(),
+                content: "print('Hello, World!')".into(),
             }
         );
     }
@@ -1,21 +1,29 @@
 use crate::ast::Node;
 use crate::tokens::BACKTICK;
+use crate::{ExtensionContext, TokenKind};
 use winnow::Result as PResult;
-use winnow::combinator::delimited;
 use winnow::prelude::*;
 use winnow::token::take_until;
 
 // ` code `
-pub fn parse_inline_code<'s, T>(input: &mut &'s str) -> PResult<Node<T>> {
+pub fn parse_inline_code<'i, E, T>(
+    input: &mut &'i str,
+    ctx: &ExtensionContext<'_, E>,
+) -> PResult<Node<'i, T>> {
     // Basic implementation: `...`
-    let content = delimited(BACKTICK, take_until(0.., BACKTICK), BACKTICK).parse_next(input)?;
-    Ok(Node::InlineCode(content.to_string()))
+    let (open, content, close) =
+        (BACKTICK.take(), take_until(0.., BACKTICK), BACKTICK.take()).parse_next(input)?;
+    ctx.token(open, TokenKind::CodeMark);
+    ctx.token(content, TokenKind::Code);
+    ctx.token(close, TokenKind::CodeMark);
+    Ok(Node::InlineCode(content.into()))
 }
 
 #[test]
 fn test_parse_inline_code() {
     let mut input = "`inline code` and more text";
-    let result: Node<()> = parse_inline_code(&mut input).unwrap();
-    assert_eq!(result, Node::InlineCode("inline code".to_string()));
+    let ctx = crate::parser::test_context(&(), 0);
+    let result: Node<()> = parse_inline_code(&mut input, &ctx).unwrap();
+    assert_eq!(result, Node::InlineCode("inline code".into()));
     assert_eq!(input, " and more text");
 }
@@ -0,0 +1,244 @@
+use super::block::parse_block;
+use crate::ast::Block;
+use crate::{CosyParserExtension, Diagnostic, ExtensionContext};
+use std::cell::RefCell;
+use std::ops::Range;
+use winnow::Result as PResult;
+
+/// A parsed document that can be updated by reparsing only the blocks an edit touches.
+///
+/// Blocks depend only on the text from their first line onward, so after an edit the parser
+/// starts at the block before the edited one (which may absorb the edited lines, e.g., a `code:`
+/// block) and stops as soon as it reaches the start of an unchanged block again.
+#[derive(Debug, Clone)]
+pub struct IncrementalDocument<T> {
+    text: String,
+    blocks: Vec<Block<'static, T>>,
+    // The byte range of each block in `text`, including its trailing newline
+    spans: Vec<Range<usize>>,
+    // The diagnostics of each block, with lines counted from its first line so that they stay
+    // valid when an edit before the block moves it
+    diagnostics: Vec<Vec<Diagnostic>>,
+}
+
+impl<T> IncrementalDocument<T> {
+    /// Parses `text` in full.
+    pub fn parse<E>(text: impl Into<String>, extension: &E) -> PResult<Self>
+    where
+        E: CosyParserExtension<Output = T>,
+    {
+        let mut document = Self {
+            text: text.into(),
+            blocks: Vec::new(),
+            spans: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        document.reparse(0, 0, |offset| offset, extension)?;
+        Ok(document)
+    }
+
+    /// Replaces `range` of the text with `replacement` and reparses the affected blocks.
+    ///
+    /// Returns the range of blocks in [`Self::blocks`] that were replaced; the blocks outside
+    /// it are the ones from before the edit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds or does not lie on char boundaries.
+    pub fn edit<E>(
+        &mut self,
+        range: Range<usize>,
+        replacement: &str,
+        extension: &E,
+    ) -> PResult<Range<usize>>
+    where
+        E: CosyParserExtension<Output = T>,
+    {
+        let containing = self
+            .spans
+            .partition_point(|span| span.start <= range.start)
+            .saturating_sub(1);
+        let first = containing.saturating_sub(1);
+        // Blocks starting after the edit may be reused once the parser reaches them
+        let unchanged = self.spans.partition_point(|span| span.start < range.end);
+
+        self.text.replace_range(range.clone(), replacement);
+        let edit_end = range.start + replacement.len();
+        self.reparse(
+            first,
+            unchanged,
+            |offset| offset - range.end + edit_end,
+            extension,
+        )
+    }
+
+    /// Returns the current text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the blocks of the current text.
+    pub fn blocks(&self) -> &[Block<'static, T>] {
+        &self.blocks
+    }
+
+    /// Returns the byte range of each block in the current text.
+    pub fn spans(&self) -> &[Range<usize>] {
+        &self.spans
+    }
+
+    /// Returns the diagnostics reported by the extension for the current blocks.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut line = 0;
+        for (span, found) in self.spans.iter().zip(&self.diagnostics) {
+            diagnostics.extend(found.iter().map(|diagnostic| Diagnostic {
+                line: line + diagnostic.line,
+                message: diagnostic.message.clone(),
+            }));
+            line += self.text[span.clone()].matches('\n').count();
+        }
+        diagnostics
+    }
+
+    /// Returns the blocks, dropping the text.
+    pub fn into_blocks(self) -> Vec<Block<'static, T>> {
+        self.blocks
+    }
+
+    // Parse from block `first` until reaching the start of an old block from `unchanged` on,
+    // whose offsets are mapped into the current text by `shift`
+    fn reparse<E>(
+        &mut self,
+        first: usize,
+        mut unchanged: usize,
+        shift: impl Fn(usize) -> usize,
+        extension: &E,
+    ) -> PResult<Range<usize>>
+    where
+        E: CosyParserExtension<Output = T>,
+    {
+        let start = self.spans.get(first).map_or(0, |span| span.start);
+        let mut line = self.text[..start].matches('\n').count();
+        let diagnostics = RefCell::new(Vec::new());
+        let ctx = ExtensionContext::new(extension, &diagnostics);
+
+        let mut input = &self.text[start..];
+        let mut blocks = Vec::new();
+        let mut spans = Vec::new();
+        let mut found = Vec::new();
+        while !input.is_empty() {
+            let offset = self.text.len() - input.len();
+            while unchanged < self.spans.len() && shift(self.spans[unchanged].start) < offset {
+                unchanged += 1;
+            }
+            if unchanged < self.spans.len() && shift(self.spans[unchanged].start) == offset {
+                break;
+            }
+
+            let rest = input;
+            let block = parse_block(&mut input, ctx.at(0, line))?;
+            let relative = diagnostics.take().into_iter().map(|diagnostic| Diagnostic {
+                line: diagnostic.line - line,
+                ..diagnostic
+            });
+            found.push(relative.collect());
+            line += rest[..rest.len() - input.len()].matches('\n').count();
+            blocks.push(block.into_owned());
+            spans.push(offset..self.text.len() - input.len());
+        }
+        if input.is_empty() {
+            unchanged = self.spans.len();
+        }
+
+        let changed = first..first + blocks.len();
+        let reused: Vec<_> = self.spans[unchanged..]
+            .iter()
+            .map(|span| shift(span.start)..shift(span.end))
+            .collect();
+        self.blocks.splice(first..unchanged, blocks);
+        self.diagnostics.splice(first..unchanged, found);
+        self.spans.truncate(first);
+        self.spans.extend(spans);
+        self.spans.extend(reused);
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str =
+        "title\nfirst\ncode:a.rs\n fn a() {}\n\nsecond [link]\ntable:t\n a\tb\nlast\n";
+
+    struct Warn;
+    impl CosyParserExtension for Warn {
+        type Output = ();
+
+        fn parse_bracket(&self, content: &str, ctx: &ExtensionContext<'_, Self>) -> Option<()> {
+            (content == "?").then(|| ctx.diagnostic("question"))
+        }
+
+        fn parse_block(&self, _content: &str, _ctx: &ExtensionContext<'_, Self>) -> Option<()> {
+            None
+        }
+    }
+
+    fn check(range: Range<usize>, replacement: &str) -> Range<usize> {
+        let mut document = IncrementalDocument::parse(INPUT, &()).unwrap();
+        let changed = document.edit(range, replacement, &()).unwrap();
+
+        let mut input_stream = document.text();
+        let expected = crate::parse(&mut input_stream, &()).unwrap();
+        assert_eq!(document.blocks(), expected);
+        let reparsed = IncrementalDocument::parse(document.text(), &()).unwrap();
+        assert_eq!(document.spans(), reparsed.spans());
+        changed
+    }
+
+    #[test]
+    fn reparse_edited_line() {
+        let at = INPUT.find("second").unwrap();
+        // The block before the edited one is reparsed too
+        assert_eq!(check(at..at + 6, "2nd"), 3..5);
+        assert_eq!(check(0..0, "the "), 0..1);
+        assert_eq!(check(0..0, ""), 0..0);
+    }
+
+    #[test]
+    fn reparse_indentation_change() {
+        // Indenting a line moves it into the code block
+        let at = INPUT.find("\n\nsecond").unwrap();
+        assert_eq!(check(at..at + 2, "\n "), 1..3);
+
+        // Removing the header splits the code block into lines
+        let at = INPUT.find("code:").unwrap();
+        check(at..at + 5, "");
+
+        // Joining lines and editing the end of the text
+        let at = INPUT.find("\nfirst").unwrap();
+        check(at..at + 1, "");
+        check(INPUT.len()..INPUT.len(), "more");
+        check(INPUT.len() - 1..INPUT.len(), "");
+        check(0..INPUT.len(), "");
+    }
+
+    #[test]
+    fn keep_diagnostics_of_unchanged_blocks() {
+        let lines = |document: &IncrementalDocument<()>| -> Vec<usize> {
+            document.diagnostics().iter().map(|d| d.line).collect()
+        };
+        let mut document = IncrementalDocument::parse("[?]\na\nb\n[?]\n", &Warn).unwrap();
+        assert_eq!(lines(&document), [0, 3]);
+
+        // Lines inserted before a block move its diagnostics
+        document.edit(4..4, "x\ny\n", &Warn).unwrap();
+        assert_eq!(lines(&document), [0, 5]);
+        // Diagnostics of reparsed blocks are replaced
+        document.edit(0..3, "ok", &Warn).unwrap();
+        assert_eq!(lines(&document), [5]);
+        document.edit(2..2, " [?]", &Warn).unwrap();
+        assert_eq!(lines(&document), [0, 5]);
+    }
+}
@@ -0,0 +1,302 @@
+use super::block::parse_block;
+use crate::ast::Block;
+use crate::tokens::{CODE_PREFIX, TABLE_PREFIX};
+use crate::{CosyParserExtension, Diagnostic, ExtensionContext};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead};
+use winnow::Result as PResult;
+use winnow::error::ContextError;
+
+/// Parses a document one block at a time.
+///
+/// The blocks are the same as those returned by [`crate::parse`], but only the block being
+/// parsed is held in memory. Iteration stops after the first error.
+pub struct BlockIter<'s, 'e, E> {
+    input: &'s str,
+    extension: &'e E,
+    line: usize,
+    diagnostics: RefCell<Vec<Diagnostic>>,
+    failed: bool,
+}
+
+impl<'s, 'e, E> BlockIter<'s, 'e, E>
+where
+    E: CosyParserExtension,
+{
+    /// Creates an iterator over the blocks of `input`.
+    pub fn new(input: &'s str, extension: &'e E) -> Self {
+        Self {
+            input,
+            extension,
+            line: 0,
+            diagnostics: RefCell::new(Vec::new()),
+            failed: false,
+        }
+    }
+
+    /// Returns the input that has not been parsed yet.
+    pub fn remaining(&self) -> &'s str {
+        self.input
+    }
+
+    /// Takes the diagnostics reported by the extension so far.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(self.diagnostics.get_mut())
+    }
+}
+
+impl<'s, E> Iterator for BlockIter<'s, '_, E>
+where
+    E: CosyParserExtension,
+{
+    type Item = PResult<Block<'s, E::Output>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.input.is_empty() {
+            return None;
+        }
+
+        let start = self.input;
+        let ctx = ExtensionContext::new(self.extension, &self.diagnostics).at(0, self.line);
+        match parse_block(&mut self.input, ctx) {
+            Ok(block) => {
+                self.line += start[..start.len() - self.input.len()]
+                    .matches('\n')
+                    .count();
+                Some(Ok(block))
+            }
+            Err(error) => {
+                self.failed = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// An error while streaming blocks from a reader.
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    Parse(ContextError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(error) => write!(f, "failed to read input: {error}"),
+            ReadError::Parse(error) => write!(f, "failed to parse input: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io(error) => Some(error),
+            ReadError::Parse(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(error: io::Error) -> Self {
+        ReadError::Io(error)
+    }
+}
+
+/// Parses blocks from lines read one at a time, e.g., from a file.
+///
+/// Only the lines of the current block are buffered: a `code:` or `table:` block is read up to
+/// the first line that is not indented deeper than it. The blocks own their text.
+pub struct BlockReader<'e, R, E>
+where
+    E: CosyParserExtension,
+{
+    reader: R,
+    extension: &'e E,
+    line: usize,
+    lookahead: Option<String>,
+    blocks: VecDeque<Block<'static, E::Output>>,
+    diagnostics: RefCell<Vec<Diagnostic>>,
+    done: bool,
+}
+
+impl<'e, R, E> BlockReader<'e, R, E>
+where
+    R: BufRead,
+    E: CosyParserExtension,
+{
+    /// Creates an iterator over the blocks read from `reader`.
+    pub fn new(reader: R, extension: &'e E) -> Self {
+        Self {
+            reader,
+            extension,
+            line: 0,
+            lookahead: None,
+            blocks: VecDeque::new(),
+            diagnostics: RefCell::new(Vec::new()),
+            done: false,
+        }
+    }
+
+    /// Takes the diagnostics reported by the extension so far.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(self.diagnostics.get_mut())
+    }
+
+    // A line including its newline, if any
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        if let Some(line) = self.lookahead.take() {
+            return Ok(Some(line));
+        }
+        let mut line = String::new();
+        match self.reader.read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line)),
+        }
+    }
+
+    // The lines of the next top-level block
+    fn read_chunk(&mut self) -> io::Result<Option<String>> {
+        let Some(mut chunk) = self.read_line()? else {
+            return Ok(None);
+        };
+
+        let indent = indent_of(&chunk);
+        let head = &chunk[indent..];
+        if !head.starts_with(CODE_PREFIX) && !head.starts_with(TABLE_PREFIX) {
+            return Ok(Some(chunk));
+        }
+
+        // The block continues while lines are indented deeper than its header
+        while let Some(line) = self.read_line()? {
+            if indent_of(&line) <= indent {
+                self.lookahead = Some(line);
+                break;
+            }
+            chunk.push_str(&line);
+        }
+        Ok(Some(chunk))
+    }
+
+    fn parse_chunk(&mut self, chunk: &str) -> PResult<()> {
+        let mut input = chunk;
+        while !input.is_empty() {
+            let start = input;
+            let ctx = ExtensionContext::new(self.extension, &self.diagnostics).at(0, self.line);
+            let block = parse_block(&mut input, ctx)?;
+            self.line += start[..start.len() - input.len()].matches('\n').count();
+            self.blocks.push_back(block.into_owned());
+        }
+        Ok(())
+    }
+}
+
+impl<R, E> Iterator for BlockReader<'_, R, E>
+where
+    R: BufRead,
+    E: CosyParserExtension,
+{
+    type Item = Result<Block<'static, E::Output>, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(block) = self.blocks.pop_front() {
+                return Some(Ok(block));
+            }
+            if self.done {
+                return None;
+            }
+
+            let result = match self.read_chunk() {
+                Ok(Some(chunk)) => self.parse_chunk(&chunk).map_err(ReadError::Parse),
+                Ok(None) => {
+                    self.done = true;
+                    continue;
+                }
+                Err(error) => Err(error.into()),
+            };
+            if let Err(error) = result {
+                self.done = true;
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+fn indent_of(line: &str) -> usize {
+    line.chars().take_while(|&c| c == ' ').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "title\n\
+        code:main.rs\n fn main() {}\n\n  \n\
+        table:t\n a\tb\n 1\t2\n\
+         > quoted [link]\n\
+        code:empty\n\
+        last";
+
+    #[test]
+    fn iterate_blocks() {
+        let mut input_stream = INPUT;
+        let document = crate::parse(&mut input_stream, &()).unwrap();
+
+        let mut blocks = BlockIter::new(INPUT, &());
+        assert_eq!(blocks.by_ref().count(), document.len());
+        assert_eq!(blocks.remaining(), "");
+
+        let blocks: Vec<_> = BlockIter::new(INPUT, &()).map(Result::unwrap).collect();
+        assert_eq!(blocks, document);
+    }
+
+    #[test]
+    fn read_blocks() {
+        let mut input_stream = INPUT;
+        let document = crate::parse(&mut input_stream, &()).unwrap();
+
+        let blocks: Vec<_> = BlockReader::new(INPUT.as_bytes(), &())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(blocks, document);
+
+        let mut input_stream = "a\n";
+        let document = crate::parse(&mut input_stream, &()).unwrap();
+        let blocks: Vec<_> = BlockReader::new("a\n".as_bytes(), &())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(blocks, document);
+    }
+
+    struct Warn;
+    impl CosyParserExtension for Warn {
+        type Output = ();
+
+        fn parse_bracket(&self, content: &str, ctx: &ExtensionContext<'_, Self>) -> Option<()> {
+            (content == "?").then(|| ctx.diagnostic("question"))
+        }
+
+        fn parse_block(&self, _content: &str, _ctx: &ExtensionContext<'_, Self>) -> Option<()> {
+            None
+        }
+    }
+
+    #[test]
+    fn report_diagnostics_by_line() {
+        let input = "code:x\n [?]\n[?]\ntable:t\n a\n [?]";
+
+        let mut blocks = BlockIter::new(input, &Warn);
+        assert_eq!(blocks.by_ref().count(), 3);
+        let lines: Vec<_> = blocks.take_diagnostics().iter().map(|d| d.line).collect();
+        assert_eq!(lines, [2, 5]);
+
+        let mut blocks = BlockReader::new(input.as_bytes(), &Warn);
+        assert_eq!(blocks.by_ref().count(), 3);
+        let lines: Vec<_> = blocks.take_diagnostics().iter().map(|d| d.line).collect();
+        assert_eq!(lines, [2, 5]);
+    }
+}
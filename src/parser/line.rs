@@ -1,19 +1,19 @@
-use crate::CosyParserExtension;
 use crate::ast::{Block, BlockContent};
+use crate::{CosyParserExtension, ExtensionContext, TokenKind};
 use winnow::Result as PResult;
 use winnow::prelude::*;
 use winnow::token::{any, take_till};
 
 use super::node::parse_nodes;
 
-pub fn parse_line<'s, E>(
-    input: &mut &'s str,
-    extension: &'s E,
-    indent: usize,
-) -> PResult<Block<E::Output>>
+pub fn parse_line<'i, E>(
+    input: &mut &'i str,
+    ctx: ExtensionContext<'_, E>,
+) -> PResult<Block<'i, E::Output>>
 where
     E: CosyParserExtension,
 {
+    let indent = ctx.indent();
     let line_content = take_till(0.., |c| c == '\n').parse_next(input)?;
 
     // Consume newline if present
@@ -21,8 +21,24 @@ where
         let _ = any.parse_next(input)?;
     }
 
+    // Indented lines such as " 1. item" are ordered list items
+    if indent > 0
+        && let Some((number, rest)) = split_number_prefix(line_content)
+    {
+        ctx.token(
+            &line_content[..line_content.len() - rest.len()],
+            TokenKind::ListNumber,
+        );
+        let mut span = rest;
+        let nodes = parse_nodes(&mut span, &ctx)?;
+        return Ok(Block {
+            indent,
+            content: BlockContent::NumberedLine { number, nodes },
+        });
+    }
+
     let mut span = line_content;
-    let nodes = parse_nodes(&mut span, extension)?;
+    let nodes = parse_nodes(&mut span, &ctx)?;
 
     Ok(Block {
         indent,
@@ -30,22 +46,33 @@ where
     })
 }
 
+fn split_number_prefix(line: &str) -> Option<(usize, &str)> {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    let rest = line[digits..].strip_prefix(". ")?;
+    Some((line[..digits].parse().ok()?, rest))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::ast::*;
+    use crate::parser::test_context;
+    use std::borrow::Cow;
 
     #[test]
     fn parse_root_line() {
         let input = "the simplest root string";
         let mut input_stream = input;
-        let result = parse_line(&mut input_stream, &(), 0);
+        let result = parse_line(&mut input_stream, test_context(&(), 0));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 0);
         assert_eq!(
             block.content,
-            BlockContent::Line(vec![Node::Text("the simplest root string".to_string())])
+            BlockContent::Line(vec![Node::Text("the simplest root string".into())])
         );
     }
 
@@ -53,13 +80,80 @@ mod tests {
     fn parse_indented_line() {
         let input = "an indented line"; // Indentation is not consumed by parse_line
         let mut input_stream = input;
-        let result = parse_line(&mut input_stream, &(), 4);
+        let result = parse_line(&mut input_stream, test_context(&(), 4));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 4);
         assert_eq!(
             block.content,
-            BlockContent::Line(vec![Node::Text("an indented line".to_string())])
+            BlockContent::Line(vec![Node::Text("an indented line".into())])
+        );
+    }
+
+    #[test]
+    fn parse_numbered_line() {
+        let input = "12. twelfth [item]";
+        let mut input_stream = input;
+        let result = parse_line(&mut input_stream, test_context(&(), 1));
+        assert!(result.is_ok());
+        let block = result.unwrap();
+        assert_eq!(block.indent, 1);
+        assert_eq!(
+            block.content,
+            BlockContent::NumberedLine {
+                number: 12,
+                nodes: vec![
+                    Node::Text("twelfth ".into()),
+                    Node::Link(Link::Page("item".into())),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_number_at_root_is_line() {
+        let input = "1. not a list";
+        let mut input_stream = input;
+        let result = parse_line(&mut input_stream, test_context(&(), 0));
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap().content,
+            BlockContent::Line(vec![Node::Text("1. not a list".into())])
+        );
+
+        let input = "2.5 is not a list";
+        let mut input_stream = input;
+        let result = parse_line(&mut input_stream, test_context(&(), 1));
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap().content,
+            BlockContent::Line(vec![Node::Text("2.5 is not a list".into())])
+        );
+    }
+
+    #[test]
+    fn parse_unclosed_markup_as_text() {
+        let input = "a $ b [c `d";
+        let mut input_stream = input;
+        let result = parse_line(&mut input_stream, test_context(&(), 0));
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap().content,
+            BlockContent::Line(vec![Node::Text("a $ b [c `d".into())])
+        );
+
+        // Markup after an unclosed bracket still parses
+        let input = "[a `b` [c";
+        let mut input_stream = input;
+        let result = parse_line(&mut input_stream, test_context(&(), 0));
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap().content,
+            BlockContent::Line(vec![
+                Node::Text("[a ".into()),
+                Node::InlineCode("b".into()),
+                Node::Text(" [c".into()),
+            ])
         );
     }
 
@@ -67,20 +161,43 @@ mod tests {
     fn parse_with_node() {
         let input = "Hello, [world.icon]!";
         let mut input_stream = input;
-        let result = parse_line(&mut input_stream, &(), 0);
+        let result = parse_line(&mut input_stream, test_context(&(), 0));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 0);
         assert_eq!(
             block.content,
             BlockContent::Line(vec![
-                Node::Text("Hello, ".to_string()),
+                Node::Text("Hello, ".into()),
                 Node::Icon {
-                    name: "world".to_string(),
+                    name: "world".into(),
                     count: 1
                 },
-                Node::Text("!".to_string()),
+                Node::Text("!".into()),
             ])
         );
     }
+
+    #[test]
+    fn parse_borrows_input() {
+        let input = String::from("see [Some Page]");
+        let mut input_stream = input.as_str();
+        let block = parse_line(&mut input_stream, test_context(&(), 0)).unwrap();
+        let BlockContent::Line(nodes) = &block.content else {
+            panic!("expected a line");
+        };
+        assert!(matches!(&nodes[0], Node::Text(Cow::Borrowed("see "))));
+        assert!(matches!(
+            &nodes[1],
+            Node::Link(Link::Page(Cow::Borrowed("Some Page")))
+        ));
+
+        let expected = block.content.clone();
+        let owned: Block<'static, ()> = block.into_owned();
+        assert_eq!(owned.content, expected);
+        assert!(matches!(
+            &owned.content,
+            BlockContent::Line(nodes) if matches!(&nodes[0], Node::Text(Cow::Owned(_)))
+        ));
+    }
 }
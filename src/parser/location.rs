@@ -0,0 +1,101 @@
+use crate::ast::Node;
+
+// [N35.68,E139.76,Z14], optionally with a place name before or after the coordinates
+pub fn parse_location<T>(content: &str) -> Option<Node<'_, T>> {
+    if let Some((lat, lon, zoom)) = parse_coordinates(content) {
+        return Some(Node::Location {
+            lat,
+            lon,
+            zoom,
+            label: None,
+        });
+    }
+
+    let (coords, label) = match content.split_once(' ') {
+        Some((coords, label)) if parse_coordinates(coords).is_some() => (coords, label),
+        _ => {
+            let (label, coords) = content.rsplit_once(' ')?;
+            (coords, label)
+        }
+    };
+    let (lat, lon, zoom) = parse_coordinates(coords)?;
+    let label = label.trim();
+
+    Some(Node::Location {
+        lat,
+        lon,
+        zoom,
+        label: (!label.is_empty()).then_some(label.into()),
+    })
+}
+
+fn parse_coordinates(s: &str) -> Option<(f64, f64, Option<u8>)> {
+    let mut parts = s.split(',');
+    let lat = parse_degree(parts.next()?, 'N', 'S', 90.0)?;
+    let lon = parse_degree(parts.next()?, 'E', 'W', 180.0)?;
+    let zoom = match parts.next() {
+        Some(z) => Some(z.strip_prefix('Z')?.parse().ok()?),
+        None => None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((lat, lon, zoom))
+}
+
+fn parse_degree(s: &str, positive: char, negative: char, max: f64) -> Option<f64> {
+    let (sign, value) = if let Some(v) = s.strip_prefix(positive) {
+        (1.0, v)
+    } else {
+        (-1.0, s.strip_prefix(negative)?)
+    };
+    // Reject forms that `f64::from_str` accepts but are not coordinates (e.g., "inf", "+1")
+    if !value.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    let value: f64 = value.parse().ok()?;
+    (value <= max).then_some(sign * value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_location_without_label() {
+        let node: Option<Node<()>> = parse_location("N35.68,E139.76,Z14");
+        assert_eq!(
+            node,
+            Some(Node::Location {
+                lat: 35.68,
+                lon: 139.76,
+                zoom: Some(14),
+                label: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_location_with_label() {
+        let expected = Some(Node::Location {
+            lat: -33.86,
+            lon: -151.21,
+            zoom: None,
+            label: Some("Somewhere".into()),
+        });
+        let node: Option<Node<()>> = parse_location("S33.86,W151.21 Somewhere");
+        assert_eq!(node, expected);
+        let node: Option<Node<()>> = parse_location("Somewhere S33.86,W151.21");
+        assert_eq!(node, expected);
+    }
+
+    #[test]
+    fn parse_non_location() {
+        let node: Option<Node<()>> = parse_location("North,East");
+        assert_eq!(node, None);
+        let node: Option<Node<()>> = parse_location("N95,E10");
+        assert_eq!(node, None);
+        let node: Option<Node<()>> = parse_location("Nice page");
+        assert_eq!(node, None);
+    }
+}
@@ -1,5 +1,6 @@
-use crate::CosyParserExtension;
 use crate::ast::Document;
+use crate::{CosyParserExtension, Diagnostic, ExtensionContext};
+use std::cell::RefCell;
 use winnow::Result as PResult;
 use winnow::combinator::repeat;
 use winnow::prelude::*;
@@ -8,17 +9,68 @@ mod block;
 mod bracket;
 mod code;
 mod code_inline;
+mod incremental;
+mod iter;
 mod line;
+mod location;
 mod node;
+mod page;
 mod quote;
 mod table;
 mod text;
+mod tokenize;
 
 use block::parse_block;
+pub use incremental::IncrementalDocument;
+pub use iter::{BlockIter, BlockReader, ReadError};
+pub(crate) use node::parse_nodes;
+#[cfg(feature = "rayon")]
+pub use page::parse_pages;
+pub use page::{PageResult, parse_page, parse_page_with_diagnostics};
+pub(crate) use tokenize::Recorder;
+pub use tokenize::{Token, TokenKind, tokenize, tokenize_with_diagnostics};
 
-pub fn parse<'s, E>(input: &mut &'s str, extension: &'s E) -> PResult<Document<E::Output>>
+pub fn parse<'s, E>(input: &mut &'s str, extension: &E) -> PResult<Document<'s, E::Output>>
 where
     E: CosyParserExtension,
 {
-    repeat(0.., |i: &mut &'s str| parse_block(i, extension)).parse_next(input)
+    parse_with_diagnostics(input, extension).map(|(document, _)| document)
+}
+
+// Parse a document and collect the diagnostics reported by the extension
+pub fn parse_with_diagnostics<'s, E>(
+    input: &mut &'s str,
+    extension: &E,
+) -> PResult<(Document<'s, E::Output>, Vec<Diagnostic>)>
+where
+    E: CosyParserExtension,
+{
+    let diagnostics = RefCell::new(Vec::new());
+    let document = parse_document(input, ExtensionContext::new(extension, &diagnostics))?;
+    Ok((document, diagnostics.into_inner()))
+}
+
+// Parse blocks starting at the line of `ctx`
+fn parse_document<'s, E>(
+    input: &mut &'s str,
+    ctx: ExtensionContext<'_, E>,
+) -> PResult<Document<'s, E::Output>>
+where
+    E: CosyParserExtension,
+{
+    let mut line = ctx.line();
+    repeat(0.., |i: &mut &'s str| {
+        let start = *i;
+        let block = parse_block(i, ctx.at(0, line))?;
+        line += start[..start.len() - i.len()].matches('\n').count();
+        Ok(block)
+    })
+    .parse_next(input)
+}
+
+#[cfg(test)]
+pub(crate) fn test_context<E>(extension: &E, indent: usize) -> ExtensionContext<'_, E> {
+    // Leaked so that tests can hold a context without owning the diagnostics
+    let diagnostics = Box::leak(Box::new(RefCell::new(Vec::new())));
+    ExtensionContext::new(extension, diagnostics).at(indent, 0)
 }
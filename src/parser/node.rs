@@ -1,6 +1,6 @@
 use super::code_inline::parse_inline_code;
-use crate::CosyParserExtension;
 use crate::ast::Node;
+use crate::{CosyParserExtension, ExtensionContext};
 use winnow::Result as PResult;
 use winnow::combinator::{alt, repeat};
 use winnow::prelude::*;
@@ -8,13 +8,26 @@ use winnow::prelude::*;
 use super::bracket::parse_bracket;
 use super::text::parse_text;
 
-pub fn parse_nodes<'s, E>(input: &mut &'s str, extension: &'s E) -> PResult<Vec<Node<E::Output>>>
+pub fn parse_nodes<'i, E>(
+    input: &mut &'i str,
+    ctx: &ExtensionContext<'_, E>,
+) -> PResult<Vec<Node<'i, E::Output>>>
 where
     E: CosyParserExtension,
 {
+    // Plain text may be followed by a custom span in one step
     repeat(
         0..,
-        alt((parse_inline_code, parse_bracket(extension), parse_text)),
+        alt((
+            (|i: &mut &'i str| parse_inline_code(i, ctx)).map(|node| (Some(node), None)),
+            parse_bracket(*ctx).map(|node| (Some(node), None)),
+            |i: &mut &'i str| parse_text(i, ctx),
+        )),
     )
+    .fold(Vec::new, |mut nodes, (first, second)| {
+        nodes.extend(first);
+        nodes.extend(second);
+        nodes
+    })
     .parse_next(input)
 }
@@ -0,0 +1,191 @@
+use super::node::parse_nodes;
+use super::parse_document;
+use crate::ast::{Page, plain_text};
+use crate::{CosyParserExtension, Diagnostic, ExtensionContext};
+use std::cell::RefCell;
+use winnow::Result as PResult;
+use winnow::prelude::*;
+use winnow::token::{any, take_till};
+
+// A parsed page along with the diagnostics reported by the extension
+pub type PageResult<'s, T> = PResult<(Page<'s, T>, Vec<Diagnostic>)>;
+
+pub fn parse_page<'s, E>(input: &mut &'s str, extension: &E) -> PResult<Page<'s, E::Output>>
+where
+    E: CosyParserExtension,
+{
+    parse_page_with_diagnostics(input, extension).map(|(page, _)| page)
+}
+
+// Parse a page and collect the diagnostics reported by the extension
+pub fn parse_page_with_diagnostics<'s, E>(
+    input: &mut &'s str,
+    extension: &E,
+) -> PageResult<'s, E::Output>
+where
+    E: CosyParserExtension,
+{
+    // Exports may start with a byte order mark
+    if let Some(rest) = input.strip_prefix('\u{feff}') {
+        *input = rest;
+    }
+
+    // The first line is always the title, even if it is indented or looks like a block
+    let title_line = take_till(0.., |c| c == '\n').parse_next(input)?;
+    if !input.is_empty() && (*input).starts_with('\n') {
+        let _ = any.parse_next(input)?;
+    }
+
+    let diagnostics = RefCell::new(Vec::new());
+    let ctx = ExtensionContext::new(extension, &diagnostics);
+
+    let mut span = title_line.trim();
+    let nodes = parse_nodes(&mut span, &ctx)?;
+    let title = plain_text(&nodes).trim().to_string();
+
+    // The body starts at the second line
+    let body = parse_document(input, ctx.at(0, 1))?;
+
+    Ok((Page { title, body }, diagnostics.into_inner()))
+}
+
+// Parse many pages on the rayon thread pool, keeping their order, along with the diagnostics
+// of each page. Each page has its own result, so that a page that fails does not abort the
+// others
+#[cfg(feature = "rayon")]
+pub fn parse_pages<'s, S, E>(pages: &'s [S], extension: &E) -> Vec<PageResult<'s, E::Output>>
+where
+    S: AsRef<str> + Sync,
+    E: CosyParserExtension + Sync,
+    E::Output: Send,
+{
+    use rayon::prelude::*;
+
+    pages
+        .par_iter()
+        .map(|page| parse_page_with_diagnostics(&mut page.as_ref(), extension))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::*;
+
+    #[test]
+    fn parse_page_with_body() {
+        let input = "My Page\nfirst line\n indented";
+        let mut input_stream = input;
+        let page = parse_page(&mut input_stream, &()).unwrap();
+        assert_eq!(page.title, "My Page");
+        assert_eq!(
+            page.body,
+            vec![
+                Block {
+                    indent: 0,
+                    content: BlockContent::Line(vec![Node::Text("first line".into())]),
+                },
+                Block {
+                    indent: 1,
+                    content: BlockContent::Line(vec![Node::Text("indented".into())]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_decorated_title() {
+        let input = "\u{feff}  [* Bold] and [Link] title\r\nbody";
+        let mut input_stream = input;
+        let page = parse_page(&mut input_stream, &()).unwrap();
+        assert_eq!(page.title, "Bold and Link title");
+        assert_eq!(page.body.len(), 1);
+    }
+
+    #[test]
+    fn parse_block_like_title() {
+        let input = "code:example.rs\n fn main() {}";
+        let mut input_stream = input;
+        let page = parse_page(&mut input_stream, &()).unwrap();
+        assert_eq!(page.title, "code:example.rs");
+        assert_eq!(
+            page.body,
+            vec![Block {
+                indent: 1,
+                content: BlockContent::Line(vec![Node::Text("fn main() {}".into())]),
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_empty_page() {
+        let mut input_stream = "";
+        let page = parse_page(&mut input_stream, &()).unwrap();
+        assert_eq!(page.title, "");
+        assert!(page.body.is_empty());
+    }
+
+    struct Warn;
+    impl CosyParserExtension for Warn {
+        type Output = ();
+
+        fn parse_bracket(&self, content: &str, ctx: &ExtensionContext<'_, Self>) -> Option<()> {
+            (content == "?").then(|| ctx.diagnostic("question"))
+        }
+
+        fn parse_block(&self, _content: &str, _ctx: &ExtensionContext<'_, Self>) -> Option<()> {
+            None
+        }
+    }
+
+    #[test]
+    fn report_page_diagnostics() {
+        let mut input_stream = "Title [?]
+body
+ [?]";
+        let (page, diagnostics) = parse_page_with_diagnostics(&mut input_stream, &Warn).unwrap();
+        assert_eq!(page.title, "Title");
+        let lines: Vec<_> = diagnostics.iter().map(|d| d.line).collect();
+        assert_eq!(lines, [0, 2]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parse_pages_in_order() {
+        let pages: Vec<String> = (0..100)
+            .map(|i| format!("Page {i}\nlinks to [Page {}]", i + 1))
+            .collect();
+        let parsed = parse_pages(&pages, &());
+        assert_eq!(parsed.len(), pages.len());
+        for (i, page) in parsed.into_iter().enumerate() {
+            let (page, _) = page.unwrap();
+            assert_eq!(page.title, format!("Page {i}"));
+            assert_eq!(
+                page.body[0].content,
+                BlockContent::Line(vec![
+                    Node::Text("links to ".into()),
+                    Node::Link(Link::Page(format!("Page {}", i + 1).into())),
+                ])
+            );
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parse_pages_with_registry() {
+        let registry = crate::dynamic::ExtensionRegistry::new();
+        let pages = ["A\n[B]", "B\n[A]"];
+        let titles: Vec<_> = parse_pages(&pages, &registry)
+            .into_iter()
+            .map(|page| page.unwrap().0.title)
+            .collect();
+        assert_eq!(titles, ["A", "B"]);
+
+        let pages = ["A\n[?]", "B", "C\n\n[?]"];
+        let lines: Vec<Vec<_>> = parse_pages(&pages, &Warn)
+            .into_iter()
+            .map(|page| page.unwrap().1.iter().map(|d| d.line).collect())
+            .collect();
+        assert_eq!(lines, [vec![1], vec![], vec![2]]);
+    }
+}
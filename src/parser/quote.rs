@@ -1,20 +1,22 @@
 use super::node::parse_nodes;
-use crate::CosyParserExtension;
 use crate::ast::{Block, BlockContent};
+use crate::tokens::GT;
+use crate::{CosyParserExtension, ExtensionContext, TokenKind};
 use winnow::Result as PResult;
 use winnow::prelude::*;
 use winnow::token::{any, take_till};
 
-pub fn parse_quote<'s, E>(
-    input: &mut &'s str,
-    extension: &'s E,
-    indent: usize,
-) -> PResult<Block<E::Output>>
+pub fn parse_quote<'i, E>(
+    input: &mut &'i str,
+    ctx: ExtensionContext<'_, E>,
+) -> PResult<Block<'i, E::Output>>
 where
     E: CosyParserExtension,
 {
+    let indent = ctx.indent();
     // Consume '>'
-    let _ = any.parse_next(input)?;
+    let mark = GT.take().parse_next(input)?;
+    ctx.token(mark, TokenKind::QuoteMark);
 
     // Determine content (rest of line)
     let line_content = take_till(0.., |c| c == '\n').parse_next(input)?;
@@ -28,10 +30,11 @@ where
     let mut span = line_content;
     // Optional: trim leading space if exists? `> text` vs `>text`
     if span.starts_with(' ') {
+        ctx.token(&span[..1], TokenKind::Separator);
         span = &span[1..];
     }
 
-    let nodes = parse_nodes(&mut span, extension)?;
+    let nodes = parse_nodes(&mut span, &ctx)?;
 
     Ok(Block {
         indent,
@@ -43,18 +46,19 @@ where
 mod tests {
     use super::*;
     use crate::ast::*;
+    use crate::parser::test_context;
 
     #[test]
     fn parse_quote_block() {
         let input = "> This is a quote line.\n";
         let mut input_stream = input;
-        let result = parse_quote(&mut input_stream, &(), 0);
+        let result = parse_quote(&mut input_stream, test_context(&(), 0));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 0);
         assert_eq!(
             block.content,
-            BlockContent::Quote(vec![Node::Text("This is a quote line.".to_string())])
+            BlockContent::Quote(vec![Node::Text("This is a quote line.".into())])
         );
     }
 
@@ -62,7 +66,7 @@ mod tests {
     fn parse_formatted_quote() {
         let input = "> [* Bold Quote] and [Linked part]\n";
         let mut input_stream = input;
-        let result = parse_quote(&mut input_stream, &(), 0);
+        let result = parse_quote(&mut input_stream, test_context(&(), 0));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 0);
@@ -70,11 +74,11 @@ mod tests {
             block.content,
             BlockContent::Quote(vec![
                 Node::Decoration {
-                    decos: "*".to_string(),
-                    nodes: vec![Node::Text("Bold Quote".to_string())]
+                    decos: "*".into(),
+                    nodes: vec![Node::Text("Bold Quote".into())]
                 },
-                Node::Text(" and ".to_string()),
-                Node::Link(Link::Page("Linked part".to_string())),
+                Node::Text(" and ".into()),
+                Node::Link(Link::Page("Linked part".into())),
             ])
         );
     }
@@ -1,23 +1,25 @@
 use super::node::parse_nodes;
-use crate::CosyParserExtension;
-use crate::ast::{Block, BlockContent};
+use crate::ast::{Block, BlockContent, TableCell};
 use crate::tokens::TABLE_PREFIX;
+use crate::{CosyParserExtension, ExtensionContext, TokenKind};
 use winnow::Result as PResult;
 use winnow::prelude::*;
 use winnow::token::{any, take_till};
 
-pub fn parse_table<'s, E>(
-    input: &mut &'s str,
-    extension: &'s E,
-    indent: usize,
-) -> PResult<Block<E::Output>>
+pub fn parse_table<'i, E>(
+    input: &mut &'i str,
+    ctx: ExtensionContext<'_, E>,
+) -> PResult<Block<'i, E::Output>>
 where
     E: CosyParserExtension,
 {
+    let indent = ctx.indent();
     // "table:name"
-    let _ = { TABLE_PREFIX }.parse_next(input)?;
+    let prefix = { TABLE_PREFIX }.parse_next(input)?;
+    ctx.token(prefix, TokenKind::BlockPrefix);
     let name_line = take_till(0.., |c| c == '\n').parse_next(input)?;
-    let name = name_line.trim().to_string();
+    ctx.token(name_line, TokenKind::BlockName);
+    let name = name_line.trim();
 
     if !input.is_empty() && (*input).starts_with('\n') {
         let _ = any.parse_next(input)?;
@@ -41,12 +43,20 @@ where
         let line = take_till(0.., |c| c == '\n').parse_next(input)?;
 
         // Parse row cells (tab separated)
+        for (i, tab) in line.match_indices('\t') {
+            ctx.token(&line[i..i + tab.len()], TokenKind::CellSeparator);
+        }
         let cells_str: Vec<&str> = line.split('\t').collect();
+        let row_ctx = ctx.at(indent, ctx.line() + rows.len() + 1);
         let mut row = Vec::new();
-        for mut cell_str in cells_str {
+        for raw in cells_str {
             // trim? usually tables align. Let's just parse.
-            let nodes = parse_nodes(&mut cell_str, extension)?;
-            row.push(nodes);
+            let mut cell_str = raw;
+            let nodes = parse_nodes(&mut cell_str, &row_ctx)?;
+            row.push(TableCell {
+                raw: raw.into(),
+                nodes,
+            });
         }
         rows.push(row);
 
@@ -59,9 +69,26 @@ where
         let _ = any.parse_next(input)?;
     }
 
+    // Pad ragged rows so that every row has the same number of cells
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize_with(columns, TableCell::empty);
+    }
+
+    let header = if rows.is_empty() {
+        Vec::new()
+    } else {
+        rows.remove(0)
+    };
+
     Ok(Block {
         indent,
-        content: BlockContent::Table { name, rows },
+        content: BlockContent::Table {
+            name: name.into(),
+            columns,
+            header,
+            rows,
+        },
     })
 }
 
@@ -69,31 +96,30 @@ where
 mod tests {
     use super::*;
     use crate::ast::*;
+    use crate::parser::test_context;
+
+    fn cell(raw: &str) -> TableCell<'_, ()> {
+        TableCell {
+            raw: raw.into(),
+            nodes: vec![Node::Text(raw.into())],
+        }
+    }
 
     #[test]
     fn parse_table_block() {
         let input = "table:MyTable\n    Cell1\tCell2\tCell3\n    Data1\tData2\tData3\n";
         let mut input_stream = input;
-        let result = parse_table(&mut input_stream, &(), 0);
+        let result = parse_table(&mut input_stream, test_context(&(), 0));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 0);
         assert_eq!(
             block.content,
             BlockContent::Table {
-                name: "MyTable".to_string(),
-                rows: vec![
-                    vec![
-                        vec![Node::Text("Cell1".to_string())],
-                        vec![Node::Text("Cell2".to_string())],
-                        vec![Node::Text("Cell3".to_string())],
-                    ],
-                    vec![
-                        vec![Node::Text("Data1".to_string())],
-                        vec![Node::Text("Data2".to_string())],
-                        vec![Node::Text("Data3".to_string())],
-                    ],
-                ],
+                name: "MyTable".into(),
+                columns: 3,
+                header: vec![cell("Cell1"), cell("Cell2"), cell("Cell3")],
+                rows: vec![vec![cell("Data1"), cell("Data2"), cell("Data3")]],
             }
         );
     }
@@ -102,25 +128,61 @@ mod tests {
     fn parse_table_without_name() {
         let input = "table:\n    A\tB\n    1\t2\n";
         let mut input_stream = input;
-        let result = parse_table(&mut input_stream, &(), 0);
+        let result = parse_table(&mut input_stream, test_context(&(), 0));
         assert!(result.is_ok());
         let block = result.unwrap();
         assert_eq!(block.indent, 0);
         assert_eq!(
             block.content,
             BlockContent::Table {
-                name: "".to_string(),
-                rows: vec![
-                    vec![
-                        vec![Node::Text("A".to_string())],
-                        vec![Node::Text("B".to_string())],
-                    ],
-                    vec![
-                        vec![Node::Text("1".to_string())],
-                        vec![Node::Text("2".to_string())],
-                    ],
-                ],
+                name: "".into(),
+                columns: 2,
+                header: vec![cell("A"), cell("B")],
+                rows: vec![vec![cell("1"), cell("2")]],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_ragged_table() {
+        let input = "table:ragged\n A\n 1\t[link]\t3\n";
+        let mut input_stream = input;
+        let result = parse_table(&mut input_stream, test_context(&(), 0));
+        assert!(result.is_ok());
+        let block = result.unwrap();
+        assert_eq!(
+            block.content,
+            BlockContent::Table {
+                name: "ragged".into(),
+                columns: 3,
+                header: vec![cell("A"), TableCell::empty(), TableCell::empty()],
+                rows: vec![vec![
+                    cell("1"),
+                    TableCell {
+                        raw: "[link]".into(),
+                        nodes: vec![Node::Link(Link::Page("link".into()))],
+                    },
+                    cell("3"),
+                ]],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_empty_table() {
+        let input = "table:empty\nnext line";
+        let mut input_stream = input;
+        let result = parse_table(&mut input_stream, test_context(&(), 0));
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap().content,
+            BlockContent::Table {
+                name: "empty".into(),
+                columns: 0,
+                header: vec![],
+                rows: vec![],
             }
         );
+        assert_eq!(input_stream, "next line");
     }
 }
@@ -1,26 +1,104 @@
 use crate::ast::Node;
-use crate::tokens::{DOLLAR, LBRACKET};
+use crate::tokens::{BACKTICK, LBRACKET, RBRACKET};
+use crate::{CosyParserExtension, ExtensionContext, TokenKind};
 use winnow::Result as PResult;
 use winnow::prelude::*;
-use winnow::token::take_till;
+use winnow::token::any;
 
-pub fn parse_text<'s, T>(input: &mut &'s str) -> PResult<Node<T>> {
-    // Stop at [, $, `
-    // Also stop at \n because blocks are line-based generally, but parse_nodes handles lines.
-    // parse_nodes calls this.
-    // We should probably consume at least one char to avoid infinite loop if we get stuck,
-    // but take_till(1..) ensures we take at least one char or fail.
-    // If next char is [, $, or `, take_till will fail (return 0 len match if 1..).
-    // This allows alt() in parse_nodes to try other parsers.
+// The plain text up to the next markup, and the custom span that ends it if an extension claims
+// one
+pub type TextNodes<'i, T> = (Option<Node<'i, T>>, Option<Node<'i, T>>);
 
-    let text = take_till(1.., |c| c == LBRACKET || c == DOLLAR || c == '`').parse_next(input)?;
-    Ok(Node::Text(text.to_string()))
+pub fn parse_text<'i, E>(
+    input: &mut &'i str,
+    ctx: &ExtensionContext<'_, E>,
+) -> PResult<TextNodes<'i, E::Output>>
+where
+    E: CosyParserExtension,
+{
+    // Stop at the next [ or ` that starts markup, so that parse_nodes can parse it. One that
+    // cannot, a `[` with no `]` after it or a backtick with no other after it, is kept as text
+    // rather than dropping the rest of the line, as is the one at the start, which is only reached
+    // when its markup failed
+    let mut peek = *input;
+    let first = any.parse_next(&mut peek)?;
+    let mut end = first.len_utf8();
+    let (mut bracket_closes, mut code_closes) = (true, true);
+    let end = loop {
+        let Some(i) = input[end..].find([LBRACKET, BACKTICK]) else {
+            break input.len();
+        };
+        end += i;
+        let after = &input[end + 1..];
+        let closes = if input[end..].starts_with(LBRACKET) {
+            bracket_closes = bracket_closes && after.contains(RBRACKET);
+            bracket_closes
+        } else {
+            code_closes = code_closes && after.contains(BACKTICK);
+            code_closes
+        };
+        if closes {
+            break end;
+        }
+        end += 1;
+    };
+    let text = &input[..end];
+
+    // Extensions may claim a span of the text, which ends the plain text. A declined span stays
+    // part of the plain text, and the search goes on after it
+    let extension = ctx.extension();
+    let mut searched = 0;
+    let (len, claimed) = loop {
+        let rest = &text[searched..];
+        let found = extension.find_inline(rest).filter(|span| {
+            let range = &span.range;
+            range.start < range.end
+                && range.end <= rest.len()
+                && rest.is_char_boundary(range.start)
+                && rest.is_char_boundary(range.end)
+        });
+        let Some(span) = found else {
+            break (text.len(), None);
+        };
+        let range = searched + span.range.start..searched + span.range.end;
+        match extension.parse_inline_span(rest, span, &ctx.unrecorded()) {
+            Some(custom) => break (range.start, Some((range, custom))),
+            None => searched = range.end,
+        }
+    };
+
+    let plain = &text[..len];
+    record_text(ctx, plain);
+    let plain = (!plain.is_empty()).then(|| Node::Text(plain.into()));
+    let (end, custom) = match claimed {
+        Some((range, custom)) => {
+            ctx.token(&text[range.clone()], TokenKind::Custom);
+            (range.end, Some(Node::Custom(custom)))
+        }
+        None => (len, None),
+    };
+    *input = &input[end..];
+    Ok((plain, custom))
+}
+
+// Record plain text, in which a `[` is one that failed to open a bracket
+fn record_text<E: ?Sized>(ctx: &ExtensionContext<'_, E>, text: &str) {
+    let mut rest = text;
+    while let Some(i) = rest.find(LBRACKET) {
+        ctx.text(&rest[..i]);
+        ctx.token(&rest[i..i + 1], TokenKind::UnclosedBracket);
+        rest = &rest[i + 1..];
+    }
+    ctx.text(rest);
 }
 
 #[test]
 fn test_parse_text() {
     let mut input = "これはテストです。[リンク]";
-    let result: Node<()> = parse_text(&mut input).unwrap();
-    assert_eq!(result, Node::Text("これはテストです。".to_string()));
+    let result = parse_text(&mut input, &crate::parser::test_context(&(), 0)).unwrap();
+    assert_eq!(
+        result,
+        (Some(Node::Text("これはテストです。".into())), None)
+    );
     assert_eq!(input, "[リンク]");
 }
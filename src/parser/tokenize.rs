@@ -0,0 +1,378 @@
+use super::parse_document;
+use crate::{CosyParserExtension, Diagnostic, ExtensionContext};
+use std::cell::RefCell;
+use std::ops::Range;
+
+/// The kind of a [`Token`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// The spaces at the start of a line.
+    Indent,
+    /// A line break.
+    Newline,
+    /// Plain text.
+    Text,
+    /// The `[` and `]` around a bracket.
+    Bracket,
+    /// A `[` without a matching `]`, which is plain text.
+    UnclosedBracket,
+    /// A space separating the parts of a bracket (e.g., after `*` in `[* bold]`).
+    Separator,
+    /// The decoration characters of a decoration (e.g., `*-` in `[*- text]`).
+    Decoration,
+    /// Plain text inside a decoration.
+    DecoratedText,
+    /// The page name of a page link, including any anchor.
+    PageLink,
+    /// A URL: an external link, an image or an embed.
+    Url,
+    /// Plain text in the label of a URL link.
+    LinkLabel,
+    /// An icon, including the `.icon` suffix.
+    Icon,
+    /// A map location, including any place name.
+    Location,
+    /// The backticks around inline code.
+    CodeMark,
+    /// Inline code, or a line of a code block.
+    Code,
+    /// The `$` of a math bracket.
+    MathMark,
+    /// A TeX expression.
+    Math,
+    /// The `code:` or `table:` prefix of a block.
+    BlockPrefix,
+    /// The filename of a code block or the name of a table.
+    BlockName,
+    /// The tab between table cells.
+    CellSeparator,
+    /// The `>` of a quote.
+    QuoteMark,
+    /// The number and `. ` of a numbered line.
+    ListNumber,
+    /// Text claimed by the extension.
+    Custom,
+}
+
+/// A span of the input and its kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// The byte range of the token in the input.
+    pub range: Range<usize>,
+    pub kind: TokenKind,
+}
+
+/// Splits `input` into tokens, line by line, for syntax highlighting.
+///
+/// The tokens of a line are in order and cover all of it, including its line break, so together
+/// they cover every byte of the input: the delimiters that the AST drops get tokens of their
+/// own. The tokens are recorded by the parser while it parses `input` as [`crate::parse`] does,
+/// with `extension`, so they always agree with the AST.
+pub fn tokenize<E>(input: &str, extension: &E) -> Vec<Vec<Token>>
+where
+    E: CosyParserExtension,
+{
+    tokenize_with_diagnostics(input, extension).0
+}
+
+/// Like [`tokenize`], but also returns the diagnostics reported by the extension.
+pub fn tokenize_with_diagnostics<E>(
+    input: &str,
+    extension: &E,
+) -> (Vec<Vec<Token>>, Vec<Diagnostic>)
+where
+    E: CosyParserExtension,
+{
+    let recorder = Recorder::new(input);
+    let diagnostics = RefCell::new(Vec::new());
+    let ctx = ExtensionContext::new(extension, &diagnostics).recording(&recorder);
+    // Input the parser stops at is covered as plain text
+    let mut rest = input;
+    let _ = parse_document(&mut rest, ctx);
+    (recorder.into_lines(input), diagnostics.into_inner())
+}
+
+// The tokens recorded by the parsers, each for the delimiters or content it consumes. What no
+// parser records, such as indentation and line breaks, is filled in afterwards
+pub(crate) struct Recorder {
+    // The addresses of the input, to find the offset of the slices of it that are recorded
+    input: Range<usize>,
+    tokens: RefCell<Vec<Token>>,
+}
+
+impl Recorder {
+    fn new(input: &str) -> Self {
+        let start = input.as_ptr() as usize;
+        Recorder {
+            input: start..start + input.len(),
+            tokens: RefCell::new(Vec::new()),
+        }
+    }
+
+    // Record `span` if it is a non-empty slice of the input
+    pub(crate) fn push(&self, span: &str, kind: TokenKind) {
+        let start = span.as_ptr() as usize;
+        if span.is_empty() || start < self.input.start || start + span.len() > self.input.end {
+            return;
+        }
+        let start = start - self.input.start;
+        self.tokens.borrow_mut().push(Token {
+            range: start..start + span.len(),
+            kind,
+        });
+    }
+
+    // Split the tokens into lines, filling the gaps between them
+    fn into_lines(self, input: &str) -> Vec<Vec<Token>> {
+        let mut tokens = self.tokens.into_inner();
+        tokens.sort_by_key(|token| token.range.start);
+        let mut tokens = tokens.into_iter().peekable();
+
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for line in input.split_inclusive('\n') {
+            let end = start + line.trim_end_matches('\n').len();
+            let mut filled = Vec::new();
+            let mut i = start;
+            while let Some(token) = tokens.next_if(|token| token.range.start < end) {
+                // Tokens are never nested or span lines, but a stray one must not break the
+                // coverage
+                if token.range.start < i || token.range.end > end {
+                    continue;
+                }
+                fill(&mut filled, input, start, i..token.range.start);
+                push(&mut filled, token.range.clone(), token.kind);
+                i = token.range.end;
+            }
+            fill(&mut filled, input, start, i..end);
+            push(&mut filled, end..start + line.len(), TokenKind::Newline);
+            lines.push(filled);
+            start += line.len();
+        }
+        lines
+    }
+}
+
+// Cover a gap in the line starting at `line`: spaces at the start of the line are its indent,
+// and anything else is text that the parser left as it is
+fn fill(tokens: &mut Vec<Token>, input: &str, line: usize, gap: Range<usize>) {
+    let mut start = gap.start;
+    if start == line {
+        start += input[gap.clone()].len() - input[gap.clone()].trim_start_matches(' ').len();
+        push(tokens, gap.start..start, TokenKind::Indent);
+    }
+    push(tokens, start..gap.end, TokenKind::Text);
+}
+
+fn push(tokens: &mut Vec<Token>, range: Range<usize>, kind: TokenKind) {
+    if range.is_empty() {
+        return;
+    }
+    // Runs of text split by the parser (e.g., around a span an extension declined) are merged
+    let mergeable = matches!(
+        kind,
+        TokenKind::Text | TokenKind::DecoratedText | TokenKind::LinkLabel
+    );
+    match tokens.last_mut() {
+        Some(last) if mergeable && last.kind == kind && last.range.end == range.start => {
+            last.range.end = range.end;
+        }
+        _ => tokens.push(Token { range, kind }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InlineSpan;
+    use TokenKind::*;
+
+    fn tokens(input: &str) -> Vec<Vec<(&str, TokenKind)>> {
+        tokenize(input, &())
+            .into_iter()
+            .map(|line| {
+                line.into_iter()
+                    .map(|token| (&input[token.range], token.kind))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn assert_covers(input: &str) {
+        let mut end = 0;
+        for token in tokenize(input, &()).into_iter().flatten() {
+            assert_eq!(token.range.start, end, "gap before {token:?} in {input:?}");
+            end = token.range.end;
+        }
+        assert_eq!(end, input.len());
+    }
+
+    #[test]
+    fn tokenize_inline_markup() {
+        assert_eq!(
+            tokens("a [*- b [Page#x]] `c` [$ x^2] [d.icon]\n"),
+            vec![vec![
+                ("a ", Text),
+                ("[", Bracket),
+                ("*-", Decoration),
+                (" ", Separator),
+                ("b ", DecoratedText),
+                ("[", Bracket),
+                ("Page#x", PageLink),
+                ("]", Bracket),
+                ("]", Bracket),
+                (" ", Text),
+                ("`", CodeMark),
+                ("c", Code),
+                ("`", CodeMark),
+                (" ", Text),
+                ("[", Bracket),
+                ("$", MathMark),
+                (" x^2", Math),
+                ("]", Bracket),
+                (" ", Text),
+                ("[", Bracket),
+                ("d.icon", Icon),
+                ("]", Bracket),
+                ("\n", Newline),
+            ]]
+        );
+    }
+
+    #[test]
+    fn tokenize_links() {
+        assert_eq!(
+            tokens(
+                "[Example  https://example.com] [https://example.com/a.png https://example.com]"
+            ),
+            vec![vec![
+                ("[", Bracket),
+                ("Example", LinkLabel),
+                ("  ", Separator),
+                ("https://example.com", Url),
+                ("]", Bracket),
+                (" ", Text),
+                ("[", Bracket),
+                ("https://example.com/a.png", Url),
+                (" ", Separator),
+                ("https://example.com", Url),
+                ("]", Bracket),
+            ]]
+        );
+    }
+
+    #[test]
+    fn tokenize_blocks() {
+        assert_eq!(
+            tokens("code:a.rs\n  [x]\ntable:t\n a\t[b]\n> q\n 1. one"),
+            vec![
+                vec![("code:", BlockPrefix), ("a.rs", BlockName), ("\n", Newline)],
+                vec![("  ", Indent), ("[x]", Code), ("\n", Newline)],
+                vec![("table:", BlockPrefix), ("t", BlockName), ("\n", Newline)],
+                vec![
+                    (" ", Indent),
+                    ("a", Text),
+                    ("\t", CellSeparator),
+                    ("[", Bracket),
+                    ("b", PageLink),
+                    ("]", Bracket),
+                    ("\n", Newline),
+                ],
+                vec![
+                    (">", QuoteMark),
+                    (" ", Separator),
+                    ("q", Text),
+                    ("\n", Newline)
+                ],
+                vec![(" ", Indent), ("1. ", ListNumber), ("one", Text)],
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_unclosed_markup() {
+        assert_eq!(
+            tokens("[a $ `b"),
+            vec![vec![("[", UnclosedBracket), ("a $ `b", Text)]]
+        );
+        for input in [
+            "",
+            "\n\n",
+            "日本語 [* [リンク] `コード`]\r\n [N35.68,E139.76,Z14 東京] [",
+            "[$ x] [[nested] [a]]]",
+            " code:\n  x\n\n table:\n  a\tb\tc\n",
+        ] {
+            assert_covers(input);
+        }
+    }
+
+    struct Mention;
+    impl CosyParserExtension for Mention {
+        type Output = ();
+
+        fn parse_bracket(&self, content: &str, _ctx: &ExtensionContext<'_, Self>) -> Option<()> {
+            content.starts_with("{ ").then_some(())
+        }
+
+        fn parse_block(&self, _content: &str, _ctx: &ExtensionContext<'_, Self>) -> Option<()> {
+            None
+        }
+
+        fn find_inline(&self, text: &str) -> Option<InlineSpan> {
+            let start = text.find('@')?;
+            let len = text[start..].find(' ').unwrap_or(text.len() - start);
+            Some(InlineSpan::new(start..start + len))
+        }
+
+        fn parse_inline_span(
+            &self,
+            _text: &str,
+            _span: InlineSpan,
+            _ctx: &ExtensionContext<'_, Self>,
+        ) -> Option<()> {
+            Some(())
+        }
+    }
+
+    #[test]
+    fn tokenize_reports_diagnostics() {
+        struct Warn;
+        impl CosyParserExtension for Warn {
+            type Output = ();
+
+            fn parse_bracket(&self, content: &str, ctx: &ExtensionContext<'_, Self>) -> Option<()> {
+                (content == "?").then(|| ctx.diagnostic("question"))
+            }
+
+            fn parse_block(&self, _content: &str, _ctx: &ExtensionContext<'_, Self>) -> Option<()> {
+                None
+            }
+        }
+
+        let (lines, diagnostics) = tokenize_with_diagnostics("a\n[?]", &Warn);
+        assert_eq!(lines[1][1].kind, Custom);
+        let lines: Vec<_> = diagnostics.iter().map(|d| d.line).collect();
+        assert_eq!(lines, [1]);
+    }
+
+    #[test]
+    fn tokenize_extension_syntax() {
+        let input = "hi @cat [{ meow]";
+        let tokens: Vec<_> = tokenize(input, &Mention)
+            .remove(0)
+            .into_iter()
+            .map(|token| (&input[token.range], token.kind))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                ("hi ", Text),
+                ("@cat", Custom),
+                (" ", Text),
+                ("[", Bracket),
+                ("{ meow", Custom),
+                ("]", Bracket),
+            ]
+        );
+    }
+}
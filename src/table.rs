@@ -0,0 +1,287 @@
+//! Conversion between `table:` blocks and CSV/TSV.
+//!
+//! Cells are exported as their raw Cosense markup, so links and decorations inside cells
+//! survive a round trip through a spreadsheet.
+
+use crate::ast::{BlockContent, TableCell};
+use crate::tokens::TABLE_PREFIX;
+use std::fmt;
+
+/// An error raised while reading CSV or TSV.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CsvError {
+    /// A quoted field is not closed before the end of input.
+    UnterminatedQuote {
+        /// The 1-based line number where the field starts.
+        line: usize,
+    },
+    /// A quoted field is followed by something other than a delimiter or line break.
+    UnexpectedCharacter {
+        /// The 1-based line number of the character.
+        line: usize,
+        /// The unexpected character.
+        found: char,
+    },
+    /// The first field of a record starts with a space, which a table would take as indentation.
+    LeadingSpace {
+        /// The 1-based index of the record.
+        record: usize,
+    },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::UnterminatedQuote { line } => {
+                write!(f, "unterminated quoted field starting at line {line}")
+            }
+            CsvError::UnexpectedCharacter { line, found } => {
+                write!(f, "unexpected {found:?} after quoted field at line {line}")
+            }
+            CsvError::LeadingSpace { record } => {
+                write!(f, "record {record} starts with a space")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+/// Serializes a table block to RFC 4180 CSV, with the header as the first record.
+///
+/// Returns `None` if `content` is not a table.
+pub fn to_csv<T>(content: &BlockContent<T>) -> Option<String> {
+    let mut out = String::new();
+    for record in records(content)? {
+        let fields: Vec<String> = record.iter().map(|cell| quote_csv(&cell.raw)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    Some(out)
+}
+
+/// Serializes a table block to TSV, with the header as the first record.
+///
+/// Returns `None` if `content` is not a table.
+pub fn to_tsv<T>(content: &BlockContent<T>) -> Option<String> {
+    let mut out = String::new();
+    for record in records(content)? {
+        let fields: Vec<&str> = record.iter().map(|cell| cell.raw.as_ref()).collect();
+        out.push_str(&fields.join("\t"));
+        out.push('\n');
+    }
+    Some(out)
+}
+
+/// Converts CSV into `table:` block markup indented by `indent` spaces.
+///
+/// Fields are taken as Cosense markup. Tabs and line breaks inside fields are replaced by
+/// spaces, since they would otherwise split cells or rows. Records whose first field starts with
+/// a space are rejected, since the space would be read back as indentation.
+pub fn csv_to_table(name: &str, csv: &str, indent: usize) -> Result<String, CsvError> {
+    records_to_table(name, &parse_csv(csv)?, indent)
+}
+
+/// Converts TSV into `table:` block markup indented by `indent` spaces.
+///
+/// Like [`csv_to_table`], records whose first field starts with a space are rejected.
+pub fn tsv_to_table(name: &str, tsv: &str, indent: usize) -> Result<String, CsvError> {
+    let records: Vec<Vec<String>> = tsv
+        .lines()
+        .map(|line| line.split('\t').map(str::to_string).collect())
+        .collect();
+    records_to_table(name, &records, indent)
+}
+
+fn records<'c, 'a, T>(content: &'c BlockContent<'a, T>) -> Option<Vec<&'c [TableCell<'a, T>]>> {
+    let BlockContent::Table { header, rows, .. } = content else {
+        return None;
+    };
+    let mut records = Vec::with_capacity(rows.len() + 1);
+    if !header.is_empty() {
+        records.push(header.as_slice());
+    }
+    records.extend(rows.iter().map(Vec::as_slice));
+    Some(records)
+}
+
+fn quote_csv(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn records_to_table(
+    name: &str,
+    records: &[Vec<String>],
+    indent: usize,
+) -> Result<String, CsvError> {
+    let mut out = format!("{}{TABLE_PREFIX}{name}\n", " ".repeat(indent));
+    let padding = " ".repeat(indent + 1);
+    for (i, record) in records.iter().enumerate() {
+        let cells: Vec<String> = record
+            .iter()
+            .map(|field| field.replace(['\t', '\r', '\n'], " "))
+            .collect();
+        if cells.first().is_some_and(|cell| cell.starts_with(' ')) {
+            return Err(CsvError::LeadingSpace { record: i + 1 });
+        }
+        out.push_str(&padding);
+        out.push_str(&cells.join("\t"));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+// Parses RFC 4180 CSV, accepting both CRLF and LF line breaks
+fn parse_csv(csv: &str) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    // Whether the last record has started, even if only with an empty quoted field
+    let mut started = false;
+    let mut chars = csv.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        started = c != '\n';
+        match c {
+            '"' if field.is_empty() => {
+                let start = line;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            field.push(c);
+                        }
+                        None => return Err(CsvError::UnterminatedQuote { line: start }),
+                    }
+                }
+                match chars.peek() {
+                    None | Some(',' | '\r' | '\n') => {}
+                    Some(&found) => return Err(CsvError::UnexpectedCharacter { line, found }),
+                }
+            }
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+                line += 1;
+            }
+            c => field.push(c),
+        }
+    }
+
+    // The last record may lack a trailing line break
+    if started {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_table(input: &str) -> BlockContent<'_, ()> {
+        let mut input_stream = input;
+        let mut document = crate::parse(&mut input_stream, &()).unwrap();
+        document.remove(0).content
+    }
+
+    #[test]
+    fn export_csv() {
+        let table = parse_table("table:t\n name\tnote\n [Page]\tsays \"hi\", twice\n x");
+        assert_eq!(
+            to_csv(&table).unwrap(),
+            "name,note\r\n[Page],\"says \"\"hi\"\", twice\"\r\nx,\r\n"
+        );
+    }
+
+    #[test]
+    fn export_tsv() {
+        let table = parse_table("table:t\n a\tb\n [* c]\td");
+        assert_eq!(to_tsv(&table).unwrap(), "a\tb\n[* c]\td\n");
+    }
+
+    #[test]
+    fn export_non_table() {
+        let line = parse_table("just a line");
+        assert_eq!(to_csv(&line), None);
+    }
+
+    #[test]
+    fn import_csv() {
+        let csv = "name,note\r\n[Page],\"multi\nline, \"\"quoted\"\"\"\r\n";
+        assert_eq!(
+            csv_to_table("imported", csv, 1).unwrap(),
+            " table:imported\n  name\tnote\n  [Page]\tmulti line, \"quoted\"\n"
+        );
+    }
+
+    #[test]
+    fn import_csv_round_trip() {
+        let markup = csv_to_table("t", "a,b\n[link],2", 0).unwrap();
+        let table = parse_table(&markup);
+        assert_eq!(to_csv(&table).unwrap(), "a,b\r\n[link],2\r\n");
+    }
+
+    #[test]
+    fn import_csv_round_trip_edge_records() {
+        // A trailing record that is a single empty field
+        let markup = csv_to_table("t", "a\r\n\"\"", 0).unwrap();
+        assert_eq!(markup, "table:t\n a\n \n");
+        assert_eq!(to_csv(&parse_table(&markup)).unwrap(), "a\r\n\r\n");
+
+        // Leading spaces would be read back as indentation
+        assert_eq!(
+            csv_to_table("t", "a,b\n  c,d", 0),
+            Err(CsvError::LeadingSpace { record: 2 })
+        );
+        assert_eq!(
+            csv_to_table("t", "\"\tc\"", 0),
+            Err(CsvError::LeadingSpace { record: 1 })
+        );
+        let markup = csv_to_table("t", "a,  b", 0).unwrap();
+        assert_eq!(to_csv(&parse_table(&markup)).unwrap(), "a,  b\r\n");
+    }
+
+    #[test]
+    fn import_tsv() {
+        assert_eq!(
+            tsv_to_table("t", "a\tb\n1\t2\n", 0).unwrap(),
+            "table:t\n a\tb\n 1\t2\n"
+        );
+        assert_eq!(
+            tsv_to_table("t", "a\n b", 0),
+            Err(CsvError::LeadingSpace { record: 2 })
+        );
+    }
+
+    #[test]
+    fn import_invalid_csv() {
+        assert_eq!(
+            csv_to_table("t", "a\n\"open", 0),
+            Err(CsvError::UnterminatedQuote { line: 2 })
+        );
+        assert_eq!(
+            csv_to_table("t", "\"a\"b", 0),
+            Err(CsvError::UnexpectedCharacter {
+                line: 1,
+                found: 'b'
+            })
+        );
+    }
+}
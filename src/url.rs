@@ -1,32 +1,294 @@
-use mime_guess;
-use url::Url;
+//! URL classification for bracketed URLs.
+//!
+//! Cosense embeds some URLs in place (images, videos, tweets, ...) instead of rendering them as
+//! plain links. [`infer_url_kind`] decides which kind of embed a URL is, and [`UrlClassifier`]
+//! lets applications teach the parser about additional hosts.
 
-#[derive(Debug, PartialEq, Eq)]
+pub use url::Url;
+
+/// The kind of content a URL points to.
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum UrlKind {
+    /// An image (by extension, or a known extension-less image host such as Gyazo).
     Image,
+    /// A video file (e.g., `.mp4`).
+    Video,
+    /// An audio file (e.g., `.mp3`).
+    Audio,
+    /// A YouTube video.
+    YouTube {
+        /// The video id.
+        id: String,
+        /// The start time in seconds, if specified by `t=` or `start=`.
+        start: Option<u32>,
+    },
+    /// A Vimeo video.
+    Vimeo {
+        /// The video id.
+        id: String,
+    },
+    /// A post on Twitter/X.
+    Twitter {
+        /// The status id.
+        id: String,
+    },
+    /// A Spotify resource.
+    Spotify {
+        /// The resource type (e.g., "track", "album", "playlist").
+        kind: String,
+        /// The resource id.
+        id: String,
+    },
+    /// An embed recognized by a user-registered [`UrlClassifier`].
+    Custom {
+        /// The name of the provider (e.g., "internal-video").
+        provider: String,
+        /// The provider-specific id.
+        id: String,
+    },
+    /// Any other URL, rendered as a plain link.
     Other,
 }
 
+/// A classifier that recognizes URLs of a particular host or format.
+///
+/// Classifiers are consulted before the built-in rules, so they can also override them.
+/// Closures of type `Fn(&Url) -> Option<UrlKind>` implement this trait.
+pub trait UrlClassifier {
+    /// Returns the kind of the URL, or `None` to leave it to the next classifier.
+    fn classify(&self, url: &Url) -> Option<UrlKind>;
+}
+
+impl<F> UrlClassifier for F
+where
+    F: Fn(&Url) -> Option<UrlKind>,
+{
+    fn classify(&self, url: &Url) -> Option<UrlKind> {
+        self(url)
+    }
+}
+
+// The unit classifier recognizes nothing, leaving everything to the built-in rules.
+impl UrlClassifier for () {
+    fn classify(&self, _url: &Url) -> Option<UrlKind> {
+        None
+    }
+}
+
+/// An ordered set of [`UrlClassifier`]s, where the first match wins.
+#[derive(Default)]
+pub struct UrlClassifiers {
+    classifiers: Vec<Box<dyn UrlClassifier + Send + Sync>>,
+}
+
+impl UrlClassifiers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a classifier. Classifiers registered earlier take precedence.
+    pub fn register(
+        &mut self,
+        classifier: impl UrlClassifier + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.classifiers.push(Box::new(classifier));
+        self
+    }
+}
+
+impl UrlClassifier for UrlClassifiers {
+    fn classify(&self, url: &Url) -> Option<UrlKind> {
+        self.classifiers.iter().find_map(|c| c.classify(url))
+    }
+}
+
+// Image hosts whose URLs carry no file extension
+const GYAZO_HOSTS: [&str; 2] = ["gyazo.com", "www.gyazo.com"];
+const SCRAPBOX_HOSTS: [&str; 2] = ["scrapbox.io", "cosen.se"];
+
+const YOUTUBE_HOSTS: [&str; 4] = [
+    "youtube.com",
+    "www.youtube.com",
+    "m.youtube.com",
+    "music.youtube.com",
+];
+const YOUTUBE_SHORT_HOST: &str = "youtu.be";
+const VIMEO_HOSTS: [&str; 2] = ["vimeo.com", "www.vimeo.com"];
+const TWITTER_HOSTS: [&str; 5] = [
+    "twitter.com",
+    "www.twitter.com",
+    "mobile.twitter.com",
+    "x.com",
+    "www.x.com",
+];
+const SPOTIFY_HOST: &str = "open.spotify.com";
+const SPOTIFY_KINDS: [&str; 6] = ["track", "album", "playlist", "episode", "show", "artist"];
+
+/// Infers the kind of a URL using the built-in rules only.
+///
+/// Returns `None` if `s` is not a URL.
 pub fn infer_url_kind(s: &str) -> Option<UrlKind> {
-    if let Ok(url) = Url::parse(s) {
-        if let Some(ext) = url
-            .path_segments()
-            .and_then(|segments| segments.last())
-            .and_then(|name| name.split('.').last())
-        {
-            let mime = mime_guess::from_ext(ext).first_or_octet_stream();
-            if mime.type_() == mime::IMAGE {
-                return Some(UrlKind::Image);
-            } else {
-                return Some(UrlKind::Other);
-            }
-        } else {
-            return Some(UrlKind::Other);
-        }
+    infer_url_kind_with(s, &())
+}
+
+/// Infers the kind of a URL, consulting `classifier` before the built-in rules.
+///
+/// Returns `None` if `s` is not a URL.
+pub fn infer_url_kind_with(s: &str, classifier: &dyn UrlClassifier) -> Option<UrlKind> {
+    let url = Url::parse(s).ok()?;
+
+    if let Some(kind) = classifier.classify(&url) {
+        return Some(kind);
+    }
+    if is_extensionless_image(&url) {
+        return Some(UrlKind::Image);
+    }
+    if let Some(kind) = classify_service(&url) {
+        return Some(kind);
+    }
+
+    let ext = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| name.split('.').next_back());
+    let Some(ext) = ext else {
+        return Some(UrlKind::Other);
+    };
+
+    let mime = mime_guess::from_ext(ext).first_or_octet_stream();
+    match mime.type_() {
+        mime::IMAGE => Some(UrlKind::Image),
+        mime::VIDEO => Some(UrlKind::Video),
+        mime::AUDIO => Some(UrlKind::Audio),
+        _ => Some(UrlKind::Other),
+    }
+}
+
+/// Returns the OpenStreetMap URL showing a location.
+pub fn openstreetmap_url(lat: f64, lon: f64, zoom: Option<u8>) -> String {
+    let zoom = zoom.unwrap_or(14);
+    format!("https://www.openstreetmap.org/?mlat={lat}&mlon={lon}#map={zoom}/{lat}/{lon}")
+}
+
+// Gyazo (https://gyazo.com/<hash>) and Scrapbox file (https://scrapbox.io/files/<id>) URLs
+// are rendered as images even though the path has no extension.
+fn is_extensionless_image(url: &Url) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let segments = path_segments(url);
+
+    if GYAZO_HOSTS.contains(&host) {
+        return matches!(segments.as_slice(), [hash] if is_gyazo_hash(hash));
+    }
+    if SCRAPBOX_HOSTS.contains(&host) {
+        return matches!(segments.as_slice(), ["files", id] if !id.contains('.'));
+    }
+    false
+}
+
+fn is_gyazo_hash(s: &str) -> bool {
+    s.len() == 32 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn classify_service(url: &Url) -> Option<UrlKind> {
+    let host = url.host_str()?;
+    let segments = path_segments(url);
+
+    if YOUTUBE_HOSTS.contains(&host) {
+        let id = match segments.as_slice() {
+            ["watch"] => query_param(url, "v")?,
+            ["embed" | "shorts" | "live", id] => id.to_string(),
+            _ => return None,
+        };
+        return Some(UrlKind::YouTube {
+            id,
+            start: youtube_start(url),
+        });
+    }
+    if host == YOUTUBE_SHORT_HOST {
+        let [id] = segments.as_slice() else {
+            return None;
+        };
+        return Some(UrlKind::YouTube {
+            id: id.to_string(),
+            start: youtube_start(url),
+        });
+    }
+    if VIMEO_HOSTS.contains(&host) {
+        let [id] = segments.as_slice() else {
+            return None;
+        };
+        return is_numeric(id).then(|| UrlKind::Vimeo { id: id.to_string() });
+    }
+    if TWITTER_HOSTS.contains(&host) {
+        let [_user, "status", id, ..] = segments.as_slice() else {
+            return None;
+        };
+        return is_numeric(id).then(|| UrlKind::Twitter { id: id.to_string() });
+    }
+    if host == SPOTIFY_HOST {
+        // Localized URLs look like /intl-ja/track/<id>
+        let segments = match segments.as_slice() {
+            [locale, rest @ ..] if locale.starts_with("intl-") => rest,
+            segments => segments,
+        };
+        let [kind, id] = segments else {
+            return None;
+        };
+        return SPOTIFY_KINDS.contains(kind).then(|| UrlKind::Spotify {
+            kind: kind.to_string(),
+            id: id.to_string(),
+        });
     }
     None
 }
 
+fn path_segments(url: &Url) -> Vec<&str> {
+    url.path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+fn query_param(url: &Url, key: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.into_owned())
+        .filter(|v| !v.is_empty())
+}
+
+// `t=90`, `t=90s`, `t=1m30s`, `t=1h2m3s` or `start=90`
+fn youtube_start(url: &Url) -> Option<u32> {
+    let value = query_param(url, "t").or_else(|| query_param(url, "start"))?;
+    if is_numeric(&value) {
+        return value.parse().ok();
+    }
+
+    let mut seconds: u32 = 0;
+    let mut digits = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let n: u32 = digits.parse().ok()?;
+        digits.clear();
+        // Times too large for `u32` are ignored rather than wrapped
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        seconds = n.checked_mul(unit)?.checked_add(seconds)?;
+    }
+    digits.is_empty().then_some(seconds)
+}
+
+fn is_numeric(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
+
 #[test]
 fn test_infer_url_kind() {
     assert_eq!(
@@ -40,6 +302,138 @@ fn test_infer_url_kind() {
     assert_eq!(infer_url_kind("not a url"), None);
 }
 
+#[test]
+fn test_infer_extensionless_image() {
+    assert_eq!(
+        infer_url_kind("https://gyazo.com/0123456789abcdef0123456789abcdef"),
+        Some(UrlKind::Image)
+    );
+    assert_eq!(
+        infer_url_kind("https://scrapbox.io/files/6123abcd4567ef0012345678"),
+        Some(UrlKind::Image)
+    );
+    assert_eq!(
+        infer_url_kind("https://scrapbox.io/files/6123abcd4567ef0012345678.pdf"),
+        Some(UrlKind::Other)
+    );
+    assert_eq!(
+        infer_url_kind("https://gyazo.com/captures"),
+        Some(UrlKind::Other)
+    );
+    assert_eq!(
+        infer_url_kind("https://scrapbox.io/help/files"),
+        Some(UrlKind::Other)
+    );
+}
+
+#[test]
+fn test_infer_media_files() {
+    assert_eq!(
+        infer_url_kind("https://example.com/movie.mp4"),
+        Some(UrlKind::Video)
+    );
+    assert_eq!(
+        infer_url_kind("https://example.com/song.mp3"),
+        Some(UrlKind::Audio)
+    );
+}
+
+#[test]
+fn test_infer_youtube() {
+    let expected = |start| {
+        Some(UrlKind::YouTube {
+            id: "dQw4w9WgXcQ".to_string(),
+            start,
+        })
+    };
+    assert_eq!(
+        infer_url_kind("https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+        expected(None)
+    );
+    assert_eq!(
+        infer_url_kind("https://youtu.be/dQw4w9WgXcQ?t=90"),
+        expected(Some(90))
+    );
+    assert_eq!(
+        infer_url_kind("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1m30s"),
+        expected(Some(90))
+    );
+    assert_eq!(
+        infer_url_kind("https://youtube.com/shorts/dQw4w9WgXcQ"),
+        expected(None)
+    );
+    assert_eq!(
+        infer_url_kind("https://www.youtube.com/feed/trending"),
+        Some(UrlKind::Other)
+    );
+}
+
+#[test]
+fn test_youtube_start_overflow() {
+    // Start times that do not fit in `u32` are dropped instead of overflowing
+    for url in [
+        "https://youtu.be/dQw4w9WgXcQ?t=9999999h",
+        "https://youtu.be/dQw4w9WgXcQ?t=4294967295s1s",
+        "https://youtu.be/dQw4w9WgXcQ?t=99999999999",
+    ] {
+        assert_eq!(
+            infer_url_kind(url),
+            Some(UrlKind::YouTube {
+                id: "dQw4w9WgXcQ".to_string(),
+                start: None,
+            })
+        );
+    }
+    let mut input = "[https://youtu.be/abc?t=9999999h]";
+    assert!(crate::parse(&mut input, &()).is_ok());
+}
+
+#[test]
+fn test_infer_services() {
+    assert_eq!(
+        infer_url_kind("https://vimeo.com/76979871"),
+        Some(UrlKind::Vimeo {
+            id: "76979871".to_string()
+        })
+    );
+    assert_eq!(
+        infer_url_kind("https://x.com/rustlang/status/1234567890"),
+        Some(UrlKind::Twitter {
+            id: "1234567890".to_string()
+        })
+    );
+    assert_eq!(
+        infer_url_kind("https://open.spotify.com/intl-ja/track/4uLU6hMCjMI75M1A2tKUQC"),
+        Some(UrlKind::Spotify {
+            kind: "track".to_string(),
+            id: "4uLU6hMCjMI75M1A2tKUQC".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_custom_classifier() {
+    let mut classifiers = UrlClassifiers::new();
+    classifiers.register(|url: &Url| {
+        (url.host_str() == Some("video.example.com")).then(|| UrlKind::Custom {
+            provider: "internal-video".to_string(),
+            id: url.path().trim_start_matches('/').to_string(),
+        })
+    });
+
+    assert_eq!(
+        infer_url_kind_with("https://video.example.com/abc", &classifiers),
+        Some(UrlKind::Custom {
+            provider: "internal-video".to_string(),
+            id: "abc".to_string()
+        })
+    );
+    assert_eq!(
+        infer_url_kind_with("https://example.com/abc", &classifiers),
+        Some(UrlKind::Other)
+    );
+}
+
 pub fn is_url(s: &str) -> bool {
     Url::parse(s).is_ok()
 }